@@ -1,4 +1,4 @@
 #!/usr/bin/env bash
 dir=$(dirname "$0")
 cd "$dir"
-cargo +nightly bench "$@"
+cargo bench "$@"
@@ -0,0 +1,8 @@
+#!/usr/bin/env bash
+# the cost of the `safe` feature: benchmarks the default build, then the safe one against it
+dir=$(dirname "$0")
+cd "$dir"
+baseline=$(mktemp --suffix=.json)
+trap 'rm -f "$baseline"' EXIT
+cargo bench -- --json "$baseline" "$@" >/dev/null || exit
+cargo bench --features safe -- --baseline "$baseline" "$@"
@@ -1,60 +1,62 @@
-#![feature(test)]
-
-extern crate test;
-use test::Bencher;
-
-use least_8::{implementation, make_list};
-
-#[bench]
-fn naive(b: &mut Bencher) {
-    let l = make_list();
-    b.iter(|| {
-        let l8 = implementation::naive(&l);
-        assert_eq!(vec![4, 5, 15, 22, 28, 31, 37, 38], l8);
-    })
-}
-
-#[bench]
-fn optimized(b: &mut Bencher) {
-    let l = make_list();
-    b.iter(|| {
-        let l8 = implementation::optimized(&l);
-        assert_eq!(vec![4, 5, 15, 22, 28, 31, 37, 38], l8);
-    })
-}
-
-#[bench]
-fn thread_optimized(b: &mut Bencher) {
-    let l = make_list();
-    b.iter(|| {
-        let l8 = implementation::thread_optimized(&l);
-        assert_eq!(vec![4, 5, 15, 22, 28, 31, 37, 38], l8);
-    })
-}
-
-#[bench]
-fn cheat_optimized(b: &mut Bencher) {
-    let l = make_list();
-    b.iter(|| {
-        let l8 = implementation::cheat_optimized(&l);
-        assert_eq!(vec![4, 5, 15, 22, 28, 31, 37, 38], l8);
-    })
-}
-
-#[bench]
-fn less_cheat_optimized(b: &mut Bencher) {
-    let l = make_list();
-    b.iter(|| {
-        let l8 = implementation::less_cheat_optimized(&l);
-        assert_eq!(vec![4, 5, 15, 22, 28, 31, 37, 38], l8);
-    })
+use least_8::bench::{self, Config};
+
+// usage: cargo bench -- [STRATEGY_FILTER] [--sizes 1024,65536] [--dist make_list,uniform]
+//                       [--samples 50] [--warmup-ms 200]
+//                       [--json out.json] [--csv out.csv] [--baseline old.json] [--threshold 10%]
+// exits with 1 if some strategy got slower than the baseline by more than the threshold
+fn main() {
+    let config = match Config::from_args(std::env::args().skip(1)) {
+        Ok(config) => config,
+        Err(err) => fail(2, &err),
+    };
+    let baseline = config
+        .baseline
+        .as_ref()
+        .map(|path| bench::load_baseline(path).unwrap_or_else(|err| fail(2, &err)));
+
+    println!("{}", bench::format_header());
+    let measurements = bench::run(&config, |measurement| {
+        println!("{}", bench::format_row(measurement))
+    });
+
+    for (path, content) in [
+        (&config.json_output, bench::to_json(&measurements)),
+        (&config.csv_output, bench::to_csv(&measurements)),
+    ] {
+        if let Some(path) = path {
+            if let Err(err) = std::fs::write(path, content) {
+                fail(2, &format!("failed to write {}: {err}", path.display()));
+            }
+        }
+    }
+
+    if let Some(baseline) = baseline {
+        let comparisons = bench::compare(&measurements, &baseline);
+        println!();
+        println!(
+            "{:<22} {:<13} {:>9} {:>14} {:>14} {:>10}",
+            "strategy", "distribution", "size", "baseline ns", "median ns", "change"
+        );
+        for comparison in &comparisons {
+            println!("{}", bench::format_comparison(comparison, config.threshold));
+        }
+        let regressions = comparisons
+            .iter()
+            .filter(|comparison| comparison.is_regression(config.threshold))
+            .count();
+        if regressions > 0 {
+            fail(
+                1,
+                &format!(
+                    "{regressions} regression(s) beyond the {:.1}% noise threshold",
+                    config.threshold * 100.0
+                ),
+            );
+        }
+    }
 }
 
-#[bench]
-fn non_cheat_optimized(b: &mut Bencher) {
-    let l = make_list();
-    b.iter(|| {
-        let l8 = implementation::non_cheat_optimized(&l);
-        assert_eq!(vec![4, 5, 15, 22, 28, 31, 37, 38], l8);
-    })
+fn fail(code: i32, msg: &str) -> ! {
+    eprintln!("{msg}");
+    std::process::exit(code)
 }
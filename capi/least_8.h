@@ -0,0 +1,42 @@
+/* C interface of the least_8 crate, kept in sync with capi/src/lib.rs by hand.
+ * Link with -lleast8 (and -lpthread -ldl -lm for the static library). */
+#ifndef LEAST_8_H
+#define LEAST_8_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* The largest k accepted by least8_u32 and least8_new. */
+#define LEAST8_MAX_K ((size_t)1 << 20)
+/* Returned by least8_u32 for a k above LEAST8_MAX_K. */
+#define LEAST8_ERROR ((size_t)-1)
+
+/* Writes the min(k, len) smallest values of l, ascending, to out and returns
+ * their count, or LEAST8_ERROR if k exceeds LEAST8_MAX_K. out must have room
+ * for k values. */
+size_t least8_u32(const uint32_t *l, size_t len, uint32_t *out, size_t k);
+
+/* Streaming accumulator of the k smallest values pushed so far. Handles are
+ * not thread safe; use one per thread and merge them. */
+typedef struct least8_accumulator least8_accumulator;
+
+/* Returns NULL if k exceeds LEAST8_MAX_K. */
+least8_accumulator *least8_new(size_t k);
+void least8_push_many(least8_accumulator *accu, const uint32_t *l, size_t len);
+/* Pushes the values selected by from into into; from is left untouched.
+ * Merging a handle into itself does nothing. */
+void least8_merge(least8_accumulator *into, const least8_accumulator *from);
+/* Writes up to cap selected values, ascending, to out and returns their count. */
+size_t least8_values(const least8_accumulator *accu, uint32_t *out, size_t cap);
+/* Accepts NULL. */
+void least8_free(least8_accumulator *accu);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
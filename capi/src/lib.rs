@@ -0,0 +1,103 @@
+// the C interface declared in least_8.h
+
+use std::{ptr, slice};
+
+use least_8::accumulator::Accumulator;
+
+// `LEAST8_MAX_K` in least_8.h, larger `k`s are refused rather than risking an abort on allocation
+pub const LEAST8_MAX_K: usize = 1 << 20;
+// `LEAST8_ERROR` in least_8.h
+pub const LEAST8_ERROR: usize = usize::MAX;
+
+// opaque to C, `least8_accumulator` in least_8.h
+pub struct Least8Accumulator(Accumulator);
+
+// C passes NULL for empty arrays
+unsafe fn input<'a>(l: *const u32, len: usize) -> &'a [u32] {
+    if l.is_null() || len == 0 {
+        &[]
+    } else {
+        slice::from_raw_parts(l, len)
+    }
+}
+
+unsafe fn output(out: *mut u32, values: &[u32], cap: usize) -> usize {
+    let len = values.len().min(cap);
+    if !out.is_null() && len > 0 {
+        slice::from_raw_parts_mut(out, len).copy_from_slice(&values[..len]);
+    }
+    len
+}
+
+/// # Safety
+///
+/// `l` must point to `len` values and `out` to room for `k` values, or be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn least8_u32(l: *const u32, len: usize, out: *mut u32, k: usize) -> usize {
+    if k > LEAST8_MAX_K {
+        return LEAST8_ERROR;
+    }
+    let mut accu = Accumulator::new(k);
+    accu.push_many(input(l, len));
+    output(out, &accu.values(), k)
+}
+
+#[no_mangle]
+pub extern "C" fn least8_new(k: usize) -> *mut Least8Accumulator {
+    if k > LEAST8_MAX_K {
+        return ptr::null_mut();
+    }
+    Box::into_raw(Box::new(Least8Accumulator(Accumulator::new(k))))
+}
+
+/// # Safety
+///
+/// `accu` must come from `least8_new` and `l` must point to `len` values or be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn least8_push_many(accu: *mut Least8Accumulator, l: *const u32, len: usize) {
+    if let Some(accu) = accu.as_mut() {
+        accu.0.push_many(input(l, len));
+    }
+}
+
+/// # Safety
+///
+/// Both handles must come from `least8_new`.
+#[no_mangle]
+pub unsafe extern "C" fn least8_merge(
+    into: *mut Least8Accumulator,
+    from: *const Least8Accumulator,
+) {
+    // a `&mut` and a `&` to the same accumulator would be undefined behaviour
+    if ptr::eq(into, from) {
+        return;
+    }
+    if let (Some(into), Some(from)) = (into.as_mut(), from.as_ref()) {
+        into.0.merge(&from.0);
+    }
+}
+
+/// # Safety
+///
+/// `accu` must come from `least8_new` and `out` must point to room for `cap` values or be NULL.
+#[no_mangle]
+pub unsafe extern "C" fn least8_values(
+    accu: *const Least8Accumulator,
+    out: *mut u32,
+    cap: usize,
+) -> usize {
+    match accu.as_ref() {
+        Some(accu) => output(out, &accu.0.values(), cap),
+        None => 0,
+    }
+}
+
+/// # Safety
+///
+/// `accu` must come from `least8_new` and not be used afterwards.
+#[no_mangle]
+pub unsafe extern "C" fn least8_free(accu: *mut Least8Accumulator) {
+    if !accu.is_null() {
+        drop(Box::from_raw(accu));
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+// builds tests/least_8.c against the static library with the system C compiler and runs it
+#[test]
+fn c_program() {
+    let manifest = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let tmp = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    // cargo test only promises the rlib, so build the static library on the side; a separate
+    // target directory keeps clear of the lock held by the running cargo
+    let target = tmp.join("capi");
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned());
+    let status = Command::new(cargo)
+        .args(["build", "--lib", "--manifest-path"])
+        .arg(manifest.join("Cargo.toml"))
+        .arg("--target-dir")
+        .arg(&target)
+        .status()
+        .unwrap();
+    assert!(status.success(), "failed to build the static library");
+    let lib = target.join("debug/libleast8.a");
+    let exe = tmp.join("least_8_c");
+    let cc = std::env::var("CC").unwrap_or_else(|_| "cc".to_owned());
+    let status = Command::new(&cc)
+        .arg("-Wall")
+        .arg("-Werror")
+        .arg("-I")
+        .arg(&manifest)
+        .arg(manifest.join("tests/least_8.c"))
+        .arg(&lib)
+        .args(["-lpthread", "-ldl", "-lm", "-o"])
+        .arg(&exe)
+        .status()
+        .unwrap_or_else(|err| panic!("failed to run {cc}: {err}"));
+    assert!(status.success(), "{cc} failed");
+    let output = Command::new(&exe).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!("ok\n", String::from_utf8_lossy(&output.stdout));
+}
@@ -0,0 +1,39 @@
+#include <assert.h>
+#include <stdio.h>
+#include <string.h>
+
+#include "least_8.h"
+
+int main(void) {
+    uint32_t l[20];
+    uint32_t out[8];
+    uint32_t expected[8] = {2, 3, 4, 5, 6, 7, 8, 9};
+    size_t i;
+    for (i = 0; i < 20; i++) {
+        l[i] = (uint32_t)(21 - i);
+    }
+
+    assert(least8_u32(l, 20, out, 8) == 8);
+    assert(memcmp(out, expected, sizeof(out)) == 0);
+    assert(least8_u32(l, 3, out, 8) == 3);
+    assert(out[0] == 19 && out[1] == 20 && out[2] == 21);
+    assert(least8_u32(NULL, 0, out, 8) == 0);
+    assert(least8_u32(l, 20, out, LEAST8_MAX_K + 1) == LEAST8_ERROR);
+    assert(least8_new(LEAST8_MAX_K + 1) == NULL);
+
+    least8_accumulator *a = least8_new(8);
+    least8_accumulator *b = least8_new(8);
+    least8_push_many(a, l, 10);
+    least8_push_many(b, l + 10, 10);
+    least8_merge(a, b);
+    least8_merge(a, a);
+    least8_free(b);
+    assert(least8_values(a, out, 8) == 8);
+    assert(memcmp(out, expected, sizeof(out)) == 0);
+    assert(least8_values(a, out, 2) == 2);
+    least8_free(a);
+    least8_free(NULL);
+
+    puts("ok");
+    return 0;
+}
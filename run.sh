@@ -1,4 +1,4 @@
 #!/usr/bin/env bash
 dir=$(dirname "$0")
 cd "$dir"
-cargo run --release
+cargo run --release -- --time "$@"
@@ -0,0 +1,211 @@
+use alloc::vec::Vec;
+
+// streaming form of `implementation::optimized`: a sorted insertion buffer of the `k` best keys
+// guarded by the rightmost one, fed one value at a time
+// `k` comes from callers (command line, network), so only this many slots are reserved up front and
+// the buffer grows with what actually gets selected
+const MAX_PREALLOCATED: usize = 1 << 10;
+
+#[derive(Clone, Debug)]
+pub struct Accumulator {
+    k: usize,
+    largest: bool,
+    distinct: bool,
+    // sorted ascending; for `largest` the keys are bitwise negated values, so the order flips
+    keys: Vec<u32>,
+    indices: Vec<u64>,
+    pushed: u64,
+}
+
+impl Accumulator {
+    pub fn new(k: usize) -> Self {
+        Self {
+            k,
+            largest: false,
+            distinct: false,
+            keys: Vec::with_capacity(k.min(MAX_PREALLOCATED)),
+            indices: Vec::with_capacity(k.min(MAX_PREALLOCATED)),
+            pushed: 0,
+        }
+    }
+
+    // keep the `k` largest values instead of the smallest
+    pub fn largest(mut self, largest: bool) -> Self {
+        self.largest = largest;
+        self
+    }
+
+    // a value equal to an already selected one is skipped
+    pub fn distinct(mut self, distinct: bool) -> Self {
+        self.distinct = distinct;
+        self
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn is_largest(&self) -> bool {
+        self.largest
+    }
+
+    pub fn is_distinct(&self) -> bool {
+        self.distinct
+    }
+
+    // count of values pushed so far, which is also the index the next `push` gets
+    pub fn pushed(&self) -> u64 {
+        self.pushed
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    #[inline]
+    fn key(&self, value: u32) -> u32 {
+        if self.largest {
+            !value
+        } else {
+            value
+        }
+    }
+
+    #[inline]
+    pub fn push(&mut self, value: u32) {
+        let index = self.pushed;
+        self.push_at(value, index);
+    }
+
+    pub fn push_many(&mut self, values: &[u32]) {
+        for value in values {
+            self.push(*value);
+        }
+    }
+
+    // like `push`, but with an explicit input position reported by `indices`
+    #[inline]
+    pub fn push_at(&mut self, value: u32, index: u64) {
+        self.pushed = self.pushed.max(index + 1);
+        let key = self.key(value);
+        // rightmost guard
+        if self.keys.len() == self.k && self.keys.last().is_none_or(|right| key >= *right) {
+            return;
+        }
+        // after equal keys, so the earliest of equal values wins
+        let pos = self.keys.partition_point(|k| *k <= key);
+        if self.distinct && pos > 0 && self.keys[pos - 1] == key {
+            return;
+        }
+        if self.keys.len() == self.k {
+            self.keys.pop();
+            self.indices.pop();
+        }
+        self.keys.insert(pos, key);
+        self.indices.insert(pos, index);
+    }
+
+    // `other` should select the same way; its indices are taken as they are
+    pub fn merge(&mut self, other: &Accumulator) {
+        for (key, index) in other.keys.iter().zip(&other.indices) {
+            self.push_at(other.key(*key), *index);
+        }
+    }
+
+    // once `k` values are selected, only a value strictly better than this one gets in
+    pub fn threshold(&self) -> Option<u32> {
+        (self.keys.len() == self.k)
+            .then(|| self.keys.last().map(|key| self.key(*key)))
+            .flatten()
+    }
+
+    // best first: ascending, or descending for `largest`
+    pub fn values(&self) -> Vec<u32> {
+        self.keys.iter().map(|key| self.key(*key)).collect()
+    }
+
+    pub fn indices(&self) -> &[u64] {
+        &self.indices
+    }
+
+    pub fn reset(&mut self) {
+        self.keys.clear();
+        self.indices.clear();
+        self.pushed = 0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{implementation, make_list};
+
+    #[test]
+    fn same_as_naive() {
+        let l = make_list();
+        let mut accu = Accumulator::new(8);
+        accu.push_many(&l);
+        assert_eq!(implementation::naive(&l), accu.values());
+        assert_eq!(Some(38), accu.threshold());
+        for (value, index) in accu.values().iter().zip(accu.indices()) {
+            assert_eq!(*value, l[*index as usize]);
+        }
+    }
+
+    #[test]
+    fn largest_distinct() {
+        let mut accu = Accumulator::new(3).largest(true).distinct(true);
+        accu.push_many(&[5, 9, 1, 9, 7, 3, 7]);
+        assert_eq!(vec![9, 7, 5], accu.values());
+        assert_eq!(&[1, 4, 0], accu.indices());
+        assert_eq!(Some(5), accu.threshold());
+    }
+
+    #[test]
+    fn merge_keeps_indices() {
+        let l = make_list();
+        let (left, right) = l.split_at(l.len() / 2);
+        let mut accu = Accumulator::new(8);
+        accu.push_many(left);
+        let mut other = Accumulator::new(8);
+        for (i, value) in right.iter().enumerate() {
+            other.push_at(*value, (left.len() + i) as u64);
+        }
+        accu.merge(&other);
+        let mut whole = Accumulator::new(8);
+        whole.push_many(&l);
+        assert_eq!(whole.values(), accu.values());
+        assert_eq!(whole.indices(), accu.indices());
+    }
+
+    #[test]
+    fn k_zero_and_short_input() {
+        let mut accu = Accumulator::new(0);
+        accu.push_many(&[1, 2, 3]);
+        assert!(accu.is_empty());
+        assert_eq!(None, accu.threshold());
+
+        let mut accu = Accumulator::new(8);
+        accu.push_many(&[3, 1, 2]);
+        assert_eq!(vec![1, 2, 3], accu.values());
+        assert_eq!(None, accu.threshold());
+    }
+
+    #[test]
+    fn huge_k() {
+        for k in [usize::MAX, 4_000_000_000_000] {
+            let mut accu = Accumulator::new(k);
+            accu.push_many(&[3, 1, 2]);
+            assert_eq!(vec![1, 2, 3], accu.values());
+            assert_eq!(None, accu.threshold());
+        }
+        let l = make_list();
+        let mut accu = Accumulator::new(usize::MAX).largest(true);
+        accu.push_many(&l);
+        assert_eq!(l.len(), accu.len());
+    }
+}
@@ -0,0 +1,555 @@
+use std::hint::black_box;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::dataset::{Distribution, DEFAULT_SEED};
+use crate::implementation::{self, Strategy, STRATEGIES};
+use crate::json::{self, Value};
+
+pub struct Config {
+    pub strategies: &'static [Strategy],
+    pub sizes: Vec<usize>,
+    pub distributions: Vec<Distribution>,
+    // run only strategies whose name contains this substring
+    pub filter: Option<String>,
+    pub warmup: Duration,
+    pub samples: usize,
+    // iterations per sample are calibrated so that each sample takes at least this long
+    pub min_sample_time: Duration,
+    pub json_output: Option<PathBuf>,
+    pub csv_output: Option<PathBuf>,
+    pub baseline: Option<PathBuf>,
+    // relative slowdown of the median tolerated as noise when comparing against the baseline
+    pub threshold: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            strategies: STRATEGIES,
+            sizes: vec![1 << 10, 1 << 16, 1 << 20],
+            distributions: Distribution::ALL.to_vec(),
+            filter: None,
+            warmup: Duration::from_millis(200),
+            samples: 50,
+            min_sample_time: Duration::from_millis(1),
+            json_output: None,
+            csv_output: None,
+            baseline: None,
+            threshold: 0.1,
+        }
+    }
+}
+
+impl Config {
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            let mut value = |name: &str| args.next().ok_or(format!("{name} needs a value"));
+            match arg.as_str() {
+                // passed by `cargo bench`
+                "--bench" => {}
+                "--sizes" => {
+                    config.sizes = parse_list(&value(&arg)?, |size| {
+                        size.parse::<usize>()
+                            .ok()
+                            .filter(|size| *size >= 8)
+                            .ok_or(format!("invalid size {size:?}, must be at least 8"))
+                    })?
+                }
+                "--dist" => {
+                    config.distributions = parse_list(&value(&arg)?, |name| {
+                        Distribution::from_name(name)
+                            .ok_or(format!("unknown distribution {name:?}"))
+                    })?
+                }
+                "--samples" => {
+                    config.samples = value(&arg)?
+                        .parse()
+                        .ok()
+                        .filter(|samples| *samples > 0)
+                        .ok_or("--samples must be a positive number")?
+                }
+                "--warmup-ms" => {
+                    config.warmup = Duration::from_millis(
+                        value(&arg)?
+                            .parse()
+                            .map_err(|_| "--warmup-ms must be a number")?,
+                    )
+                }
+                "--json" => config.json_output = Some(value(&arg)?.into()),
+                "--csv" => config.csv_output = Some(value(&arg)?.into()),
+                "--baseline" => config.baseline = Some(value(&arg)?.into()),
+                "--threshold" => {
+                    config.threshold = value(&arg)?
+                        .trim_end_matches('%')
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|pct| *pct >= 0.0)
+                        .ok_or("--threshold must be a non-negative percentage")?
+                        / 100.0
+                }
+                _ if !arg.starts_with('-') => config.filter = Some(arg),
+                _ => return Err(format!("unknown option {arg:?}")),
+            }
+        }
+        Ok(config)
+    }
+
+    fn strategies(&self) -> impl Iterator<Item = &'static Strategy> + '_ {
+        self.strategies.iter().filter(|strategy| {
+            self.filter
+                .as_ref()
+                .is_none_or(|filter| strategy.name.contains(filter.as_str()))
+        })
+    }
+}
+
+fn parse_list<T>(s: &str, f: impl Fn(&str) -> Result<T, String>) -> Result<Vec<T>, String> {
+    s.split(',').map(str::trim).map(f).collect()
+}
+
+pub struct Measurement {
+    pub strategy: &'static str,
+    pub distribution: Distribution,
+    pub size: usize,
+    pub samples: usize,
+    pub min: Duration,
+    pub median: Duration,
+    pub p95: Duration,
+}
+
+impl Measurement {
+    pub fn ns_per_element(&self) -> f64 {
+        self.median.as_nanos() as f64 / self.size as f64
+    }
+}
+
+// `on_measurement` is called as soon as a measurement is ready, so long runs show progress
+pub fn run(config: &Config, mut on_measurement: impl FnMut(&Measurement)) -> Vec<Measurement> {
+    let mut ret = vec![];
+    for &distribution in &config.distributions {
+        for &size in &config.sizes {
+            let l = distribution.generate(size, DEFAULT_SEED);
+            let expected = implementation::naive(&l);
+            for strategy in config.strategies() {
+                if strategy.exact {
+                    assert_eq!(
+                        expected,
+                        (strategy.func)(&l),
+                        "{} disagrees with naive on {} x {size}",
+                        strategy.name,
+                        distribution.name(),
+                    );
+                }
+                let measurement = measure(config, strategy, distribution, &l);
+                on_measurement(&measurement);
+                ret.push(measurement);
+            }
+        }
+    }
+    ret
+}
+
+fn measure(
+    config: &Config,
+    strategy: &'static Strategy,
+    distribution: Distribution,
+    l: &[u32],
+) -> Measurement {
+    let run_once = || black_box((strategy.func)(black_box(l)));
+
+    let start = Instant::now();
+    let mut iters_per_sample = 0u32;
+    while start.elapsed() < config.warmup || iters_per_sample == 0 {
+        run_once();
+        iters_per_sample += 1;
+    }
+    let per_iter = start.elapsed() / iters_per_sample;
+    let iters_per_sample = if per_iter.is_zero() {
+        1000
+    } else {
+        (config.min_sample_time.as_nanos() / per_iter.as_nanos()).clamp(1, 1000) as u32
+    };
+
+    let mut samples = Vec::with_capacity(config.samples);
+    for _ in 0..config.samples {
+        let start = Instant::now();
+        for _ in 0..iters_per_sample {
+            run_once();
+        }
+        samples.push(start.elapsed() / iters_per_sample);
+    }
+    samples.sort();
+    Measurement {
+        strategy: strategy.name,
+        distribution,
+        size: l.len(),
+        samples: samples.len(),
+        min: samples[0],
+        median: percentile(&samples, 50),
+        p95: percentile(&samples, 95),
+    }
+}
+
+// nearest-rank percentile of sorted samples
+fn percentile(sorted: &[Duration], p: usize) -> Duration {
+    let rank = (sorted.len() * p).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+pub fn format_header() -> String {
+    format!(
+        "{:<22} {:<13} {:>9} {:>14} {:>14} {:>14} {:>10}",
+        "strategy", "distribution", "size", "median ns", "p95 ns", "min ns", "ns/elem"
+    )
+}
+
+pub fn format_row(m: &Measurement) -> String {
+    format!(
+        "{:<22} {:<13} {:>9} {:>14} {:>14} {:>14} {:>10.3}",
+        m.strategy,
+        m.distribution.name(),
+        m.size,
+        m.median.as_nanos(),
+        m.p95.as_nanos(),
+        m.min.as_nanos(),
+        m.ns_per_element(),
+    )
+}
+
+const CSV_HEADER: &str =
+    "strategy,distribution,size,samples,median_ns,p95_ns,min_ns,ns_per_element";
+
+pub fn to_csv(measurements: &[Measurement]) -> String {
+    let mut ret = format!("{CSV_HEADER}\n");
+    for m in measurements {
+        ret += &format!(
+            "{},{},{},{},{},{},{},{:.3}\n",
+            m.strategy,
+            m.distribution.name(),
+            m.size,
+            m.samples,
+            m.median.as_nanos(),
+            m.p95.as_nanos(),
+            m.min.as_nanos(),
+            m.ns_per_element(),
+        );
+    }
+    ret
+}
+
+pub fn to_json(measurements: &[Measurement]) -> String {
+    let results = measurements
+        .iter()
+        .map(|m| {
+            Value::object([
+                ("strategy", m.strategy.into()),
+                ("distribution", m.distribution.name().into()),
+                ("size", m.size.into()),
+                ("samples", m.samples.into()),
+                ("median_ns", (m.median.as_nanos() as u64).into()),
+                ("p95_ns", (m.p95.as_nanos() as u64).into()),
+                ("min_ns", (m.min.as_nanos() as u64).into()),
+                ("ns_per_element", m.ns_per_element().into()),
+            ])
+        })
+        .collect();
+    Value::object([("results", Value::Array(results))]).to_string()
+}
+
+pub struct BaselineEntry {
+    pub strategy: String,
+    pub distribution: String,
+    pub size: usize,
+    pub median: Duration,
+}
+
+// reads either format written by `to_json`/`to_csv`, picked by the `.csv` extension
+pub fn load_baseline(path: &Path) -> Result<Vec<BaselineEntry>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|err| format!("failed to read {}: {err}", path.display()))?;
+    if path.extension().is_some_and(|ext| ext == "csv") {
+        parse_csv_baseline(&content)
+    } else {
+        parse_json_baseline(&content)
+    }
+    .map_err(|err| format!("{}: {err}", path.display()))
+}
+
+fn parse_csv_baseline(content: &str) -> Result<Vec<BaselineEntry>, String> {
+    let mut lines = content.lines();
+    let header: Vec<_> = lines.next().ok_or("empty file")?.split(',').collect();
+    let column = |name: &str| {
+        header
+            .iter()
+            .position(|column| *column == name)
+            .ok_or(format!("missing column {name:?}"))
+    };
+    let (strategy, distribution, size, median) = (
+        column("strategy")?,
+        column("distribution")?,
+        column("size")?,
+        column("median_ns")?,
+    );
+    lines
+        .filter(|line| !line.trim().is_empty())
+        .enumerate()
+        .map(|(i, line)| {
+            let fields: Vec<_> = line.split(',').collect();
+            let field = |idx: usize| {
+                fields
+                    .get(idx)
+                    .copied()
+                    .ok_or(format!("line {}: too few fields", i + 2))
+            };
+            Ok(BaselineEntry {
+                strategy: field(strategy)?.to_owned(),
+                distribution: field(distribution)?.to_owned(),
+                size: field(size)?
+                    .parse()
+                    .map_err(|_| format!("line {}: invalid size", i + 2))?,
+                median: Duration::from_nanos(
+                    field(median)?
+                        .parse()
+                        .map_err(|_| format!("line {}: invalid median_ns", i + 2))?,
+                ),
+            })
+        })
+        .collect()
+}
+
+fn parse_json_baseline(content: &str) -> Result<Vec<BaselineEntry>, String> {
+    let value = json::parse(content)?;
+    let results = value
+        .get("results")
+        .and_then(Value::as_array)
+        .ok_or("missing \"results\" array")?;
+    results
+        .iter()
+        .map(|result| {
+            let str_field = |name: &str| {
+                result
+                    .get(name)
+                    .and_then(Value::as_str)
+                    .map(str::to_owned)
+                    .ok_or(format!("result without {name:?}"))
+            };
+            let u64_field = |name: &str| {
+                result
+                    .get(name)
+                    .and_then(Value::as_u64)
+                    .ok_or(format!("result without {name:?}"))
+            };
+            Ok(BaselineEntry {
+                strategy: str_field("strategy")?,
+                distribution: str_field("distribution")?,
+                size: u64_field("size")? as usize,
+                median: Duration::from_nanos(u64_field("median_ns")?),
+            })
+        })
+        .collect()
+}
+
+pub struct Comparison<'a> {
+    pub measurement: &'a Measurement,
+    pub baseline: Duration,
+}
+
+impl Comparison<'_> {
+    // relative change of the median, positive when slower
+    pub fn change(&self) -> f64 {
+        self.measurement.median.as_nanos() as f64 / self.baseline.as_nanos().max(1) as f64 - 1.0
+    }
+
+    pub fn is_regression(&self, threshold: f64) -> bool {
+        self.change() > threshold
+    }
+}
+
+// measurements missing from the baseline are skipped
+pub fn compare<'a>(
+    measurements: &'a [Measurement],
+    baseline: &[BaselineEntry],
+) -> Vec<Comparison<'a>> {
+    measurements
+        .iter()
+        .filter_map(|measurement| {
+            baseline
+                .iter()
+                .find(|entry| {
+                    entry.strategy == measurement.strategy
+                        && entry.distribution == measurement.distribution.name()
+                        && entry.size == measurement.size
+                })
+                .map(|entry| Comparison {
+                    measurement,
+                    baseline: entry.median,
+                })
+        })
+        .collect()
+}
+
+pub fn format_comparison(comparison: &Comparison, threshold: f64) -> String {
+    let m = comparison.measurement;
+    format!(
+        "{:<22} {:<13} {:>9} {:>14} {:>14} {:>+9.1}%{}",
+        m.strategy,
+        m.distribution.name(),
+        m.size,
+        comparison.baseline.as_nanos(),
+        m.median.as_nanos(),
+        comparison.change() * 100.0,
+        if comparison.is_regression(threshold) {
+            "  REGRESSION"
+        } else {
+            ""
+        },
+    )
+}
+
+// the measurements of `distribution`, slowest first, with the speedup relative to `naive` of the
+// same distribution and size
+pub fn markdown_table(measurements: &[Measurement], distribution: Distribution) -> String {
+    let mut sorted: Vec<_> = measurements
+        .iter()
+        .filter(|m| m.distribution == distribution)
+        .collect();
+    sorted.sort_by_key(|m| std::cmp::Reverse(m.median));
+    let mut ret = String::from("| strategy | ns/iter | speedup vs `naive` |\n|---|---:|---:|\n");
+    for m in sorted {
+        let naive = measurements.iter().find(|naive| {
+            naive.strategy == "naive"
+                && naive.distribution == m.distribution
+                && naive.size == m.size
+        });
+        let speedup = naive.map_or("-".to_owned(), |naive| {
+            format!(
+                "{:.1}x",
+                naive.median.as_nanos() as f64 / m.median.as_nanos().max(1) as f64
+            )
+        });
+        ret += &format!(
+            "| `{}` | {} | {speedup} |\n",
+            m.strategy,
+            m.median.as_nanos()
+        );
+    }
+    ret
+}
+
+// replaces everything between `<!-- NAME:start -->` and `<!-- NAME:end -->`, keeping the markers
+pub fn replace_marked_section(content: &str, name: &str, section: &str) -> Result<String, String> {
+    let start_marker = format!("<!-- {name}:start -->");
+    let end_marker = format!("<!-- {name}:end -->");
+    let start = content
+        .find(&start_marker)
+        .ok_or(format!("marker {start_marker:?} not found"))?
+        + start_marker.len();
+    let end = start
+        + content[start..]
+            .find(&end_marker)
+            .ok_or(format!("marker {end_marker:?} not found"))?;
+    Ok(format!(
+        "{}\n{}{}",
+        &content[..start],
+        section,
+        &content[end..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percentile_nearest_rank() {
+        let samples: Vec<_> = (1..=20).map(Duration::from_nanos).collect();
+        assert_eq!(Duration::from_nanos(1), percentile(&samples, 0));
+        assert_eq!(Duration::from_nanos(10), percentile(&samples, 50));
+        assert_eq!(Duration::from_nanos(19), percentile(&samples, 95));
+        assert_eq!(Duration::from_nanos(20), percentile(&samples, 100));
+    }
+
+    #[test]
+    fn run_covers_every_strategy() {
+        let config = Config {
+            sizes: vec![64],
+            distributions: vec![Distribution::MakeList, Distribution::FewDistinct],
+            warmup: Duration::ZERO,
+            samples: 3,
+            min_sample_time: Duration::ZERO,
+            ..Config::default()
+        };
+        let measurements = run(&config, |_| {});
+        assert_eq!(2 * STRATEGIES.len(), measurements.len());
+        assert!(measurements
+            .iter()
+            .all(|m| m.min <= m.median && m.median <= m.p95));
+        // `run` checks the exact ones against `naive`
+        let config = Config {
+            strategies: implementation::UNGUARDED_STRATEGIES,
+            ..config
+        };
+        assert_eq!(
+            2 * implementation::UNGUARDED_STRATEGIES.len(),
+            run(&config, |_| {}).len()
+        );
+    }
+
+    fn measurement(strategy: &'static str, median_ns: u64) -> Measurement {
+        Measurement {
+            strategy,
+            distribution: Distribution::MakeList,
+            size: 1024,
+            samples: 1,
+            min: Duration::from_nanos(median_ns),
+            median: Duration::from_nanos(median_ns),
+            p95: Duration::from_nanos(median_ns),
+        }
+    }
+
+    #[test]
+    fn baseline_round_trip_and_regressions() {
+        let before = vec![measurement("naive", 1000), measurement("optimized", 100)];
+        let after = vec![measurement("naive", 1050), measurement("optimized", 150)];
+        for baseline in [
+            parse_json_baseline(&to_json(&before)).unwrap(),
+            parse_csv_baseline(&to_csv(&before)).unwrap(),
+        ] {
+            let comparisons = compare(&after, &baseline);
+            let regressions: Vec<_> = comparisons
+                .iter()
+                .filter(|c| c.is_regression(0.1))
+                .map(|c| c.measurement.strategy)
+                .collect();
+            assert_eq!(vec!["optimized"], regressions);
+        }
+    }
+
+    #[test]
+    fn readme_section() {
+        let table = markdown_table(
+            &[measurement("optimized", 100), measurement("naive", 1000)],
+            Distribution::MakeList,
+        );
+        assert_eq!(
+            "| strategy | ns/iter | speedup vs `naive` |\n|---|---:|---:|\n\
+             | `naive` | 1000 | 1.0x |\n\
+             | `optimized` | 100 | 10.0x |\n",
+            table
+        );
+        assert_eq!(
+            "| strategy | ns/iter | speedup vs `naive` |\n|---|---:|---:|\n",
+            markdown_table(&[measurement("naive", 1000)], Distribution::Uniform)
+        );
+        let readme = "# title\n<!-- a:start -->\nold\n<!-- a:end -->\n<!-- b:start -->\nold\n<!-- b:end -->\n";
+        assert_eq!(
+            "# title\n<!-- a:start -->\nold\n<!-- a:end -->\n<!-- b:start -->\nnew\n<!-- b:end -->\n",
+            replace_marked_section(readme, "b", "new\n").unwrap()
+        );
+        assert!(replace_marked_section(readme, "c", "new\n").is_err());
+        assert!(replace_marked_section("no markers", "a", "new\n").is_err());
+    }
+}
@@ -0,0 +1,625 @@
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::Instant;
+
+use crate::accumulator::Accumulator;
+use crate::dataset::{Distribution, DEFAULT_SEED};
+use crate::file::FileScan;
+use crate::format::{self, Format};
+use crate::http::HttpServer;
+use crate::implementation;
+use crate::json::{self, Value};
+use crate::output::OutputFormat;
+use crate::query::{Query, Selection, DEFAULT_K};
+use crate::server::{self, Server};
+
+pub const USAGE: &str = "\
+usage: least_8 [OPTIONS] [FILE...]
+       least_8 generate --size N -o FILE [--seed S] [--dist DIST] [--format FORMAT]
+       least_8 verify FILE [--format FORMAT]
+       least_8 serve --listen ADDR [--capacity N | --http]
+       least_8 repl
+       least_8 readme [--readme README.md] [BENCH OPTIONS]
+
+Selects the smallest numbers among unsigned 32-bit integers read from
+FILEs or stdin (also `-`).
+
+options:
+  --k N              how many values to select (default 8)
+  --strategy NAME    stream (default) or one of implementation::STRATEGIES
+  --largest          select the largest values instead
+  --distinct         skip values equal to an already selected one
+  --indices          also print the zero-based input position of each value
+  --format FORMAT    text (default, separated by whitespace or commas),
+                     le-u32, be-u32, le-u64, varint (unsigned LEB128)
+                     or csv:<column> (header name or zero-based index)
+  --output FORMAT    text (default), json, csv or ndjson; all but text also
+                     carry the strategy, input length, elapsed time and
+                     whether the strategy is exact
+  --time             time every strategy on make_list() and exit
+  -h, --help         print this help
+
+generate writes a reproducible dataset of N values drawn from DIST
+(make_list, uniform, ascending, descending, few_distinct, zipf; default
+make_list, seeded with S, default 998244353 as in make_list) in FORMAT
+(default text) to FILE, and the 8 smallest values as naive returns them
+to FILE.expected.json for later verification, without holding the
+dataset in memory.
+
+verify runs every strategy on FILE, prints their results and timings and
+flags those that disagree with naive (or with FILE.expected.json, whose
+format is used when --format is not given). Exits with 1 if a strategy
+that is meant to be exact disagrees.
+
+serve answers a line protocol on a TCP address (e.g. 127.0.0.1:7878) with
+named streaming accumulators keeping N values each (default 64, at most
+1048576), send
+HELP for the commands. With --http it answers HTTP/1.1 instead:
+POST /least?k=8&strategy=optimized with the numbers as the body (text, or
+le-u32 for application/octet-stream; also format=, largest, distinct and
+indices) returns the selection as JSON, GET /strategies lists strategies.
+
+repl reads commands from stdin to load files, push values, change the
+strategy, k, largest and distinct and show the selection, type help there.
+
+readme benchmarks every strategy on make_list(), with and without the
+rightmost guard, and rewrites the generated tables of README.md; BENCH
+OPTIONS are those of benches/bench.rs except --sizes and --dist.
+";
+
+pub struct Options {
+    pub query: Query,
+    pub format: Format,
+    pub output: OutputFormat,
+    pub time: bool,
+    pub help: bool,
+    // `-` is stdin, no inputs at all too
+    pub inputs: Vec<PathBuf>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            query: Query::default(),
+            format: Format::Text,
+            output: OutputFormat::Text,
+            time: false,
+            help: false,
+            inputs: vec![],
+        }
+    }
+}
+
+impl Options {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut options = Self::default();
+        let mut args = Args::new(args);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--k" => options.query.k = args.parse(&arg)?,
+                "--strategy" => {
+                    let name = args.value(&arg)?;
+                    options.query.strategy = if name == "stream" {
+                        None
+                    } else {
+                        Some(
+                            implementation::strategy(&name)
+                                .ok_or(format!("unknown strategy {name:?}"))?,
+                        )
+                    }
+                }
+                "--format" => options.format = Format::parse(&args.value(&arg)?)?,
+                "--output" => options.output = OutputFormat::parse(&args.value(&arg)?)?,
+                "--largest" => options.query.largest = true,
+                "--distinct" => options.query.distinct = true,
+                "--indices" => options.query.indices = true,
+                "--time" => options.time = true,
+                "-h" | "--help" => options.help = true,
+                "-" => options.inputs.push(arg.into()),
+                _ if arg.starts_with('-') => return Err(format!("unknown option {arg:?}")),
+                _ => options.inputs.push(arg.into()),
+            }
+        }
+        options.query.check()?;
+        Ok(options)
+    }
+
+    fn for_each_input(
+        &self,
+        mut f: impl FnMut(&str, Box<dyn Read>) -> Result<(), String>,
+    ) -> Result<(), String> {
+        if self.inputs.is_empty() {
+            return f("stdin", Box::new(io::stdin().lock()));
+        }
+        for path in &self.inputs {
+            if path.as_os_str() == "-" {
+                f("stdin", Box::new(io::stdin().lock()))?;
+            } else {
+                let file = File::open(path)
+                    .map_err(|err| format!("failed to open {}: {err}", path.display()))?;
+                f(&path.display().to_string(), Box::new(file))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn select(&self) -> Result<Selection, String> {
+        match self.query.strategy {
+            // packed files are scanned in chunks instead of being loaded
+            Some(strategy)
+                if self.format == Format::LeU32
+                    && !self.inputs.is_empty()
+                    && self.inputs.iter().all(|path| path.as_os_str() != "-")
+                    && matches!(strategy.name, "optimized" | "thread_optimized") =>
+            {
+                let mut input_len = 0;
+                for path in &self.inputs {
+                    input_len += std::fs::metadata(path)
+                        .map_err(|err| format!("{}: {err}", path.display()))?
+                        .len()
+                        / 4;
+                }
+                self.query.check_len(input_len)?;
+                let scan = FileScan {
+                    threads: if strategy.name == "optimized" {
+                        1
+                    } else {
+                        FileScan::default().threads
+                    },
+                    ..FileScan::default()
+                };
+                let rets = self
+                    .inputs
+                    .iter()
+                    .map(|path| {
+                        scan.run(path)
+                            .map_err(|err| format!("{}: {err}", path.display()))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Selection {
+                    values: implementation::merge(
+                        &rets.iter().map(Vec::as_slice).collect::<Vec<_>>(),
+                    ),
+                    indices: None,
+                    input_len,
+                })
+            }
+            // registered strategies need the whole input at once
+            Some(_) => {
+                let mut l = vec![];
+                self.for_each_input(|name, reader| {
+                    format::decode(&self.format, name, reader, |value| l.push(value))
+                })?;
+                self.query.run(&l)
+            }
+            None => {
+                let mut accu = self.query.accumulator();
+                self.for_each_input(|name, reader| {
+                    format::decode(&self.format, name, reader, |value| accu.push(value))
+                })?;
+                Ok(self.query.selection(&accu))
+            }
+        }
+    }
+}
+
+pub struct GenerateOptions {
+    pub size: usize,
+    pub seed: u32,
+    pub distribution: Distribution,
+    pub format: Format,
+    pub output: PathBuf,
+}
+
+impl GenerateOptions {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut size = None;
+        let mut seed = DEFAULT_SEED;
+        let mut distribution = Distribution::MakeList;
+        let mut format = Format::Text;
+        let mut output = None;
+        let mut args = Args::new(args);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--size" => size = Some(args.parse(&arg)?),
+                "--seed" => seed = args.parse(&arg)?,
+                "--dist" => {
+                    let name = args.value(&arg)?;
+                    distribution = Distribution::from_name(&name)
+                        .ok_or(format!("unknown distribution {name:?}"))?
+                }
+                "--format" => format = Format::parse(&args.value(&arg)?)?,
+                "-o" | "--output" => output = Some(PathBuf::from(args.value(&arg)?)),
+                _ => return Err(format!("unknown generate argument {arg:?}")),
+            }
+        }
+        let size = size.ok_or("generate needs --size")?;
+        // the sidecar holds what `naive` returns, and `naive` needs that many
+        if size < DEFAULT_K {
+            return Err(format!("--size must be at least {DEFAULT_K}"));
+        }
+        Ok(Self {
+            size,
+            seed,
+            distribution,
+            format,
+            output: output.ok_or("generate needs -o FILE")?,
+        })
+    }
+
+    pub fn sidecar_path(&self) -> PathBuf {
+        let mut path = self.output.clone().into_os_string();
+        path.push(".expected.json");
+        path.into()
+    }
+
+    // writes the dataset and its sidecar in one pass without holding the dataset, returns the
+    // expected values
+    pub fn generate(&self) -> Result<Vec<u32>, String> {
+        let file = File::create(&self.output)
+            .map_err(|err| format!("failed to create {}: {err}", self.output.display()))?;
+        let mut accu = Accumulator::new(DEFAULT_K);
+        let values = self
+            .distribution
+            .values(self.size, self.seed)
+            .inspect(|value| accu.push(*value));
+        format::encode_iter(&self.format, values, file)
+            .map_err(|err| format!("failed to write {}: {err}", self.output.display()))?;
+        // ascending with the earliest of equal values first, as `naive` returns them
+        let expected = accu.values();
+        let sidecar = Value::object([
+            ("size", self.size.into()),
+            ("seed", self.seed.into()),
+            ("dist", self.distribution.name().into()),
+            ("format", self.format.to_string().into()),
+            ("expected", expected.clone().into()),
+        ]);
+        let sidecar_path = self.sidecar_path();
+        std::fs::write(&sidecar_path, format!("{sidecar}\n"))
+            .map_err(|err| format!("failed to write {}: {err}", sidecar_path.display()))?;
+        Ok(expected)
+    }
+}
+
+pub struct VerifyOptions {
+    pub input: PathBuf,
+    pub format: Option<Format>,
+}
+
+impl VerifyOptions {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut input = None;
+        let mut format = None;
+        let mut args = Args::new(args);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--format" => format = Some(Format::parse(&args.value(&arg)?)?),
+                _ if arg.starts_with('-') => {
+                    return Err(format!("unknown verify argument {arg:?}"))
+                }
+                _ if input.is_none() => input = Some(PathBuf::from(arg)),
+                _ => return Err("verify takes a single FILE".to_owned()),
+            }
+        }
+        Ok(Self {
+            input: input.ok_or("verify needs a FILE")?,
+            format,
+        })
+    }
+
+    // the sidecar written by `generate`, if any: its format and expected values
+    fn sidecar(&self) -> Result<Option<(Format, Vec<u32>)>, String> {
+        let mut path = self.input.clone().into_os_string();
+        path.push(".expected.json");
+        let Ok(content) = std::fs::read_to_string(&path) else {
+            return Ok(None);
+        };
+        let invalid = || format!("{}: not a sidecar written by generate", path.display());
+        let value = json::parse(&content).map_err(|err| format!("{}: {err}", path.display()))?;
+        let format = Format::parse(
+            value
+                .get("format")
+                .and_then(Value::as_str)
+                .ok_or_else(invalid)?,
+        )?;
+        let expected = value
+            .get("expected")
+            .and_then(Value::as_array)
+            .ok_or_else(invalid)?
+            .iter()
+            .map(|v| v.as_u64().and_then(|v| u32::try_from(v).ok()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(invalid)?;
+        Ok(Some((format, expected)))
+    }
+
+    // prints the comparison table, returns `false` if an exact strategy disagrees
+    pub fn run(&self, out: &mut impl Write) -> Result<bool, String> {
+        let sidecar = self.sidecar()?;
+        let format = self
+            .format
+            .clone()
+            .or_else(|| sidecar.as_ref().map(|(format, _)| format.clone()))
+            .unwrap_or(Format::Text);
+        let file = File::open(&self.input)
+            .map_err(|err| format!("failed to open {}: {err}", self.input.display()))?;
+        let mut l = vec![];
+        format::decode(&format, &self.input.display().to_string(), file, |v| {
+            l.push(v)
+        })?;
+        if l.len() < DEFAULT_K {
+            return Err(format!(
+                "{}: needs at least {DEFAULT_K} values, got {}",
+                self.input.display(),
+                l.len()
+            ));
+        }
+
+        let write_err = |err: io::Error| err.to_string();
+        writeln!(out, "{} values ({format})", l.len()).map_err(write_err)?;
+        let mut reference = None;
+        if let Some((_, expected)) = &sidecar {
+            writeln!(out, "expected by sidecar: {expected:?}").map_err(write_err)?;
+            reference = Some(expected.clone());
+        }
+        writeln!(
+            out,
+            "{:<22} {:<6} {:>14}  {:<50} status",
+            "strategy", "exact", "time", "values"
+        )
+        .map_err(write_err)?;
+        let mut ok = true;
+        for strategy in implementation::STRATEGIES {
+            let start = Instant::now();
+            let values = (strategy.func)(&l);
+            let elapsed = start.elapsed();
+            // the first strategy is `naive`, every other one is compared with it
+            let reference = reference.get_or_insert_with(|| values.clone());
+            let status = if values == *reference {
+                "ok"
+            } else if strategy.exact {
+                ok = false;
+                "DISAGREES"
+            } else {
+                "DISAGREES (inexact strategy)"
+            };
+            writeln!(
+                out,
+                "{:<22} {:<6} {:>14}  {:<50} {status}",
+                strategy.name,
+                strategy.exact,
+                format!("{elapsed:?}"),
+                format!("{values:?}"),
+            )
+            .map_err(write_err)?;
+        }
+        Ok(ok)
+    }
+}
+
+pub struct ServeOptions {
+    pub listen: String,
+    pub capacity: usize,
+    pub http: bool,
+}
+
+impl ServeOptions {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut listen = None;
+        let mut capacity = server::DEFAULT_CAPACITY;
+        let mut http = false;
+        let mut args = Args::new(args);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--listen" => listen = Some(args.value(&arg)?),
+                "--capacity" => capacity = args.parse(&arg)?,
+                "--http" => http = true,
+                _ => return Err(format!("unknown serve argument {arg:?}")),
+            }
+        }
+        if !(1..=server::MAX_CAPACITY).contains(&capacity) {
+            return Err(format!(
+                "--capacity must be between 1 and {}",
+                server::MAX_CAPACITY
+            ));
+        }
+        Ok(Self {
+            listen: listen.ok_or("serve needs --listen ADDR")?,
+            capacity,
+            http,
+        })
+    }
+
+    pub fn serve(&self) -> Result<(), String> {
+        if self.http {
+            let server = HttpServer::bind(&self.listen)
+                .map_err(|err| format!("failed to listen on {}: {err}", self.listen))?;
+            if let Ok(addr) = server.local_addr() {
+                eprintln!("listening on http://{addr}");
+            }
+            return server.run().map_err(|err| err.to_string());
+        }
+        let server = Server::bind(&self.listen, self.capacity)
+            .map_err(|err| format!("failed to listen on {}: {err}", self.listen))?;
+        if let Ok(addr) = server.local_addr() {
+            eprintln!("listening on {addr}");
+        }
+        server.run().map_err(|err| err.to_string())
+    }
+}
+
+// accepts both `--name value` and `--name=value`
+struct Args<I> {
+    args: I,
+    inline_value: Option<String>,
+}
+
+impl<I: Iterator<Item = String>> Args<I> {
+    fn new(args: impl IntoIterator<IntoIter = I>) -> Self {
+        Self {
+            args: args.into_iter(),
+            inline_value: None,
+        }
+    }
+
+    fn next(&mut self) -> Option<String> {
+        let arg = self.args.next()?;
+        self.inline_value = None;
+        match arg.split_once('=') {
+            Some((name, value)) if name.starts_with("--") => {
+                self.inline_value = Some(value.to_owned());
+                Some(name.to_owned())
+            }
+            _ => Some(arg),
+        }
+    }
+
+    fn value(&mut self, name: &str) -> Result<String, String> {
+        self.inline_value
+            .take()
+            .or_else(|| self.args.next())
+            .ok_or(format!("{name} needs a value"))
+    }
+
+    fn parse<T: FromStr>(&mut self, name: &str) -> Result<T, String> {
+        let value = self.value(name)?;
+        value
+            .parse()
+            .map_err(|_| format!("invalid {name} value {value:?}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(s: &str) -> impl Iterator<Item = String> + '_ {
+        s.split_whitespace().map(str::to_owned)
+    }
+
+    #[test]
+    fn parse_options() {
+        let options = Options::parse(args("--k=3 --largest --distinct --indices a.txt -")).unwrap();
+        assert_eq!(3, options.query.k);
+        assert!(options.query.largest && options.query.distinct && options.query.indices);
+        assert_eq!(
+            vec![PathBuf::from("a.txt"), PathBuf::from("-")],
+            options.inputs
+        );
+
+        let options = Options::parse(args(
+            "--strategy optimized --format csv:latency --output ndjson",
+        ))
+        .unwrap();
+        assert_eq!(OutputFormat::Ndjson, options.output);
+        assert_eq!(
+            Format::Csv(format::Column::Name("latency".into())),
+            options.format
+        );
+        assert_eq!("optimized", options.query.strategy_name());
+
+        assert!(Options::parse(args("--strategy bogus")).is_err());
+        assert!(Options::parse(args("--strategy optimized --k 3")).is_err());
+        assert!(Options::parse(args("--k")).is_err());
+        assert!(Options::parse(args("--bogus")).is_err());
+    }
+
+    #[test]
+    fn serve_capacity() {
+        let options = ServeOptions::parse(args("--listen 127.0.0.1:0 --capacity 16")).unwrap();
+        assert_eq!(16, options.capacity);
+        assert!(ServeOptions::parse(args("--listen 127.0.0.1:0 --capacity 0")).is_err());
+        assert!(
+            ServeOptions::parse(args("--listen 127.0.0.1:0 --capacity 4000000000000")).is_err()
+        );
+    }
+
+    #[test]
+    fn generate_with_sidecar() {
+        let output = std::env::temp_dir().join(format!("least_8-{}-generate", std::process::id()));
+        let options = GenerateOptions::parse(
+            args("--size 1000 --seed 7 --dist zipf --format varint -o")
+                .chain([output.display().to_string()]),
+        )
+        .unwrap();
+        let expected = options.generate().unwrap();
+
+        let mut values = vec![];
+        let file = File::open(&output).unwrap();
+        format::decode(&Format::Varint, "generated", file, |v| values.push(v)).unwrap();
+        assert_eq!(Distribution::Zipf.generate(1000, 7), values);
+        assert_eq!(implementation::naive(&values), expected);
+
+        let sidecar = std::fs::read_to_string(options.sidecar_path()).unwrap();
+        assert_eq!(
+            format!(
+                "{{\"size\":1000,\"seed\":7,\"dist\":\"zipf\",\"format\":\"varint\",\"expected\":{}}}\n",
+                Value::from(expected)
+            ),
+            sidecar
+        );
+        std::fs::remove_file(options.sidecar_path()).unwrap();
+        std::fs::remove_file(output).unwrap();
+
+        assert!(GenerateOptions::parse(args("--size 4 -o x")).is_err());
+        assert!(GenerateOptions::parse(args("--size 100")).is_err());
+    }
+
+    #[test]
+    fn short_scanned_file() {
+        let input = std::env::temp_dir().join(format!("least_8-{}-short", std::process::id()));
+        format::encode(&Format::LeU32, &[3, 1, 2], File::create(&input).unwrap()).unwrap();
+        for strategy in ["optimized", "thread_optimized"] {
+            let options = Options::parse(
+                args(&format!("--strategy {strategy} --format le-u32"))
+                    .chain([input.display().to_string()]),
+            )
+            .unwrap();
+            assert_eq!(
+                Err(format!(
+                    "strategy {strategy} needs at least 8 values, got 3"
+                )),
+                options.select().map(|selection| selection.values)
+            );
+        }
+        std::fs::remove_file(&input).unwrap();
+    }
+
+    #[test]
+    fn verify_flags_disagreements() {
+        let input = std::env::temp_dir().join(format!("least_8-{}-verify", std::process::id()));
+        // 300 is beyond `cheat_optimized`, which only keeps values up to 255
+        let l: Vec<u32> = (0..100).map(|i| 300 + i).collect();
+        format::encode(&Format::BeU32, &l, File::create(&input).unwrap()).unwrap();
+        let options =
+            VerifyOptions::parse(args("--format be-u32").chain([input.display().to_string()]))
+                .unwrap();
+        let mut out = vec![];
+        assert!(options.run(&mut out).unwrap());
+        let out = String::from_utf8(out).unwrap();
+        let status = |name: &str| {
+            let line = out
+                .lines()
+                .find(|line| line.starts_with(&format!("{name} ")))
+                .unwrap();
+            line.split("  ").last().unwrap().trim().to_owned()
+        };
+        assert_eq!("ok", status("optimized"));
+        assert_eq!("DISAGREES (inexact strategy)", status("cheat_optimized"));
+
+        // a sidecar that doesn't match makes even the exact strategies disagree
+        let mut sidecar = input.clone().into_os_string();
+        sidecar.push(".expected.json");
+        std::fs::write(
+            &sidecar,
+            r#"{"format":"be-u32","expected":[1,2,3,4,5,6,7,8]}"#,
+        )
+        .unwrap();
+        let options = VerifyOptions::parse([input.display().to_string()]).unwrap();
+        assert!(!options.run(&mut vec![]).unwrap());
+        std::fs::remove_file(sidecar).unwrap();
+        std::fs::remove_file(input).unwrap();
+    }
+}
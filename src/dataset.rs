@@ -0,0 +1,206 @@
+use alloc::vec::Vec;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Distribution {
+    // the rotate-and-add sequence of `make_list`
+    MakeList,
+    Uniform,
+    Ascending,
+    Descending,
+    FewDistinct,
+    // Zipf-like with exponent 1 over the whole `u32` range: mostly small values, heavy tail
+    Zipf,
+}
+
+impl Distribution {
+    pub const ALL: [Distribution; 6] = [
+        Distribution::MakeList,
+        Distribution::Uniform,
+        Distribution::Ascending,
+        Distribution::Descending,
+        Distribution::FewDistinct,
+        Distribution::Zipf,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Distribution::MakeList => "make_list",
+            Distribution::Uniform => "uniform",
+            Distribution::Ascending => "ascending",
+            Distribution::Descending => "descending",
+            Distribution::FewDistinct => "few_distinct",
+            Distribution::Zipf => "zipf",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|dist| dist.name() == name)
+    }
+
+    pub fn generate(self, size: usize, seed: u32) -> Vec<u32> {
+        self.values(size, seed).collect()
+    }
+
+    // the values of `generate` one at a time, for datasets larger than memory
+    pub fn values(self, size: usize, seed: u32) -> Values {
+        Values {
+            distribution: self,
+            size,
+            seed,
+            i: 0,
+            num: seed,
+            rng: XorShift::new(seed),
+        }
+    }
+}
+
+pub struct Values {
+    distribution: Distribution,
+    size: usize,
+    seed: u32,
+    i: usize,
+    // the previous value of `make_list`
+    num: u32,
+    rng: XorShift,
+}
+
+impl Iterator for Values {
+    type Item = u32;
+
+    fn next(&mut self) -> Option<u32> {
+        if self.i == self.size {
+            return None;
+        }
+        let i = self.i;
+        self.i += 1;
+        Some(match self.distribution {
+            Distribution::MakeList => {
+                let num = self.num;
+                self.num = (num.rotate_left(1) as u64 + i as u64) as u32;
+                num
+            }
+            Distribution::Uniform => self.rng.next_u32(),
+            Distribution::Ascending => self.seed.wrapping_add(i as u32),
+            Distribution::Descending => self.seed.wrapping_add((self.size - 1 - i) as u32),
+            Distribution::FewDistinct => self.rng.next_u32() % 16,
+            Distribution::Zipf => zipf(self.rng.next_u32()),
+        })
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.size - self.i;
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for Values {}
+
+// 2^(i / 32) in 32.32 fixed point
+const EXP2: [u64; 33] = [
+    0x100000000,
+    0x1059b0d31,
+    0x10b5586d0,
+    0x111301d01,
+    0x1172b83c8,
+    0x11d487317,
+    0x12387a6e7,
+    0x129e9df52,
+    0x1306fe0a3,
+    0x1371a7374,
+    0x13dea64c1,
+    0x144e08606,
+    0x14bfdad53,
+    0x15342b56a,
+    0x15ab07dd5,
+    0x16247eb04,
+    0x16a09e668,
+    0x171f75e8f,
+    0x17a11473f,
+    0x182589995,
+    0x18ace5423,
+    0x193737b0d,
+    0x19c49182a,
+    0x1a5503b24,
+    0x1ae89f996,
+    0x1b7f76f30,
+    0x1c199bdd8,
+    0x1cb720dcf,
+    0x1d5818dd0,
+    0x1dfc97338,
+    0x1ea4afa2a,
+    0x1f50765b7,
+    0x200000000,
+];
+
+// inverse of the continuous 1/x density on [1, 2^32) for `r / 2^32` uniform in [0, 1): 2^(32 * r / 2^32) - 1,
+// in integers only so that a seed gives the same values on every platform
+fn zipf(r: u32) -> u32 {
+    // the top 5 bits of `r` are the integer part of the exponent, the other 27 the fraction
+    let exponent = r >> 27;
+    let fraction = r & ((1 << 27) - 1);
+    // 2^fraction from the table, interpolated linearly between its entries
+    let i = (fraction >> 22) as usize;
+    let t = u64::from(fraction & ((1 << 22) - 1));
+    let mantissa = EXP2[i] + (((EXP2[i + 1] - EXP2[i]) * t) >> 22);
+    ((mantissa << exponent) >> 32) as u32 - 1
+}
+
+pub const DEFAULT_SEED: u32 = 998_244_353; // prime, as in `make_list`
+
+struct XorShift(u32);
+
+impl XorShift {
+    fn new(seed: u32) -> Self {
+        // xorshift gets stuck on zero
+        Self(if seed == 0 { DEFAULT_SEED } else { seed })
+    }
+    fn next_u32(&mut self) -> u32 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 17;
+        x ^= x << 5;
+        self.0 = x;
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn values_one_at_a_time() {
+        assert_eq!(vec![10, 11, 12], Distribution::Ascending.generate(3, 10));
+        assert_eq!(vec![12, 11, 10], Distribution::Descending.generate(3, 10));
+        let mut values = Distribution::MakeList.values(1_000, DEFAULT_SEED);
+        assert_eq!(1_000, values.len());
+        assert_eq!(Some(DEFAULT_SEED), values.next());
+        assert_eq!(999, values.len());
+        assert_eq!(crate::make_list()[1..1_000], values.collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn zipf_is_reproducible() {
+        assert_eq!(
+            vec![
+                694,
+                194_298_630,
+                5,
+                430_227,
+                104,
+                7_652,
+                1_879_623_991,
+                926_860_351
+            ],
+            Distribution::Zipf.generate(8, DEFAULT_SEED)
+        );
+        assert_eq!(0, zipf(0));
+        assert_eq!(1, zipf(1 << 27));
+        assert_eq!(4_294_967_273, zipf(u32::MAX));
+        let mut prev = 0;
+        for r in (0..=u32::MAX).step_by(4_099) {
+            assert!(zipf(r) >= prev, "{r}");
+            prev = zipf(r);
+        }
+    }
+}
@@ -0,0 +1,106 @@
+use alloc::vec::Vec;
+
+use crate::multiset::Least8Set;
+
+// the 8 smallest of a multiset that takes values in and out in any order, e.g. prices with
+// cancellations: `O(log n)` per `insert` and `remove`, `least_8` reads the insertion buffer
+#[derive(Clone, Debug, Default)]
+pub struct DynamicLeast8 {
+    set: Least8Set,
+}
+
+impl DynamicLeast8 {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // count of values, equal values counted each
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.len() == 0
+    }
+
+    pub fn insert(&mut self, value: u32) {
+        self.set.insert(value);
+    }
+
+    // removes one of the values equal to `value`; `false` if there is none
+    pub fn remove(&mut self, value: u32) -> bool {
+        self.set.remove(value)
+    }
+
+    // ascending, like `implementation::optimized` over the values present
+    pub fn least_8(&self) -> Vec<u32> {
+        self.set.least_8().to_vec()
+    }
+
+    pub fn clear(&mut self) {
+        self.set.clear();
+    }
+}
+
+impl Extend<u32> for DynamicLeast8 {
+    fn extend<I: IntoIterator<Item = u32>>(&mut self, values: I) {
+        for value in values {
+            self.insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::implementation::{self, CAPACITY};
+    use crate::make_list;
+
+    fn naive(l: &[u32]) -> Vec<u32> {
+        if l.len() < CAPACITY {
+            implementation::naive_array(l).to_vec()
+        } else {
+            implementation::naive(l)
+        }
+    }
+
+    #[test]
+    fn same_as_naive_after_every_operation() {
+        let mut dynamic = DynamicLeast8::new();
+        let mut present: Vec<u32> = Vec::new();
+        for (i, num) in make_list()[..5_000].iter().enumerate() {
+            // mostly inserts early on, then as many removes as inserts
+            let value = num % 64;
+            let insert = num % 5 < if i < 1_000 { 4 } else { 2 };
+            if insert {
+                dynamic.insert(value);
+                present.push(value);
+            } else {
+                let position = present.iter().position(|other| *other == value);
+                assert_eq!(position.is_some(), dynamic.remove(value), "{i}");
+                if let Some(position) = position {
+                    present.swap_remove(position);
+                }
+            }
+            assert_eq!(naive(&present), dynamic.least_8(), "{i}");
+            assert_eq!(present.len(), dynamic.len());
+        }
+    }
+
+    #[test]
+    fn removing_selected_refills_from_the_rest() {
+        let mut dynamic = DynamicLeast8::new();
+        dynamic.extend([9, 1, 8, 2, 7, 3, 6, 4, 5, 10, 5]);
+        assert_eq!(vec![1, 2, 3, 4, 5, 5, 6, 7], dynamic.least_8());
+        assert!(dynamic.remove(1));
+        assert!(dynamic.remove(5));
+        assert_eq!(vec![2, 3, 4, 5, 6, 7, 8, 9], dynamic.least_8());
+        assert!(!dynamic.remove(1));
+        assert!(dynamic.remove(10));
+        assert!(dynamic.remove(2));
+        assert_eq!(vec![3, 4, 5, 6, 7, 8, 9], dynamic.least_8());
+        dynamic.clear();
+        assert!(dynamic.is_empty());
+        assert!(!dynamic.remove(3));
+    }
+}
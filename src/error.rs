@@ -0,0 +1,35 @@
+use core::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Least8Error {
+    // `found` counts the values a strategy could use, for the cheating ones only those it can pack
+    NotEnoughElements { found: usize },
+    WorkerPanicked,
+    // the OS refused a worker thread, those already started were joined
+    SpawnFailed,
+    // with `float::NanPolicy::Error`
+    NanFound { index: usize },
+    // `pairs::least_8_with` got a different number of keys and payloads
+    LengthMismatch { keys: usize, values: usize },
+}
+
+impl fmt::Display for Least8Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::NotEnoughElements { found } => write!(
+                f,
+                "not enough elements: need {}, found {found}",
+                crate::implementation::CAPACITY
+            ),
+            Self::WorkerPanicked => write!(f, "a worker thread panicked"),
+            Self::SpawnFailed => write!(f, "failed to spawn a worker thread"),
+            Self::NanFound { index } => write!(f, "NaN at index {index}"),
+            Self::LengthMismatch { keys, values } => {
+                write!(f, "{keys} keys but {values} payloads")
+            }
+        }
+    }
+}
+
+impl core::error::Error for Least8Error {}
@@ -0,0 +1,157 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::thread;
+
+use crate::implementation::{merge, optimized};
+
+const ITEM_LEN: u64 = 4;
+
+// scans a file of packed little-endian `u32`s that may not fit in memory: every worker reads
+// its own range of the file chunk by chunk into a reusable buffer, runs `optimized` on each
+// chunk and the per-chunk results are merged as `thread_optimized` merges its threads' results
+pub struct FileScan {
+    // 1 runs `optimized` on the calling thread
+    pub threads: usize,
+    // items read at once per worker
+    pub chunk_len: usize,
+}
+
+impl Default for FileScan {
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            chunk_len: 1 << 20,
+        }
+    }
+}
+
+impl FileScan {
+    pub fn run(&self, path: impl AsRef<Path>) -> io::Result<Vec<u32>> {
+        let path = path.as_ref();
+        let len = std::fs::metadata(path)?.len();
+        if len % ITEM_LEN != 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "{}: length {len} is not a multiple of {ITEM_LEN}",
+                    path.display()
+                ),
+            ));
+        }
+        let count = len / ITEM_LEN;
+        let chunk_len = self.chunk_len.max(1);
+        let threads = (self.threads as u64)
+            .min(count.div_ceil(chunk_len as u64))
+            .max(1);
+        if threads == 1 {
+            return scan_range(path, 0, count, chunk_len);
+        }
+        let per_thread = count / threads;
+        let rets = thread::scope(|scope| {
+            let mut workers = vec![];
+            let mut spawned = Ok(());
+            for i in 0..threads {
+                let start = per_thread * i;
+                let count = if i < threads - 1 {
+                    per_thread
+                } else {
+                    count - start
+                };
+                let worker = thread::Builder::new()
+                    .spawn_scoped(scope, move || scan_range(path, start, count, chunk_len));
+                match worker {
+                    Ok(worker) => workers.push(worker),
+                    Err(err) => {
+                        spawned = Err(err);
+                        break;
+                    }
+                }
+            }
+            // every worker that started is joined, also when a later one failed to
+            let rets = workers
+                .into_iter()
+                .map(|worker| {
+                    worker
+                        .join()
+                        .map_err(|_| io::Error::other("file scan worker panicked"))?
+                })
+                .collect::<io::Result<Vec<_>>>();
+            spawned.map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!("failed to spawn a file scan worker: {err}"),
+                )
+            })?;
+            rets
+        })?;
+        Ok(merge(&rets.iter().map(Vec::as_slice).collect::<Vec<_>>()))
+    }
+}
+
+pub fn least_8_file(path: impl AsRef<Path>) -> io::Result<Vec<u32>> {
+    FileScan::default().run(path)
+}
+
+fn scan_range(path: &Path, start: u64, count: u64, chunk_len: usize) -> io::Result<Vec<u32>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(start * ITEM_LEN))?;
+    let mut bytes = vec![0u8; chunk_len * ITEM_LEN as usize];
+    let mut values = Vec::with_capacity(chunk_len);
+    let mut ret = vec![];
+    let mut remaining = count;
+    while remaining > 0 {
+        let n = remaining.min(chunk_len as u64) as usize;
+        let bytes = &mut bytes[..n * ITEM_LEN as usize];
+        file.read_exact(bytes)?;
+        values.clear();
+        values.extend(
+            bytes
+                .chunks_exact(ITEM_LEN as usize)
+                .map(|item| u32::from_le_bytes(item.try_into().unwrap())),
+        );
+        ret = merge(&[&ret, &optimized(&values)]);
+        remaining -= n as u64;
+    }
+    Ok(ret)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{implementation, make_list};
+
+    fn write_temp(name: &str, l: &[u32]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("least_8-{}-{name}", std::process::id()));
+        let bytes: Vec<u8> = l.iter().flat_map(|v| v.to_le_bytes()).collect();
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn same_as_naive() {
+        let l = make_list();
+        let path = write_temp("file_scan", &l);
+        for threads in [1, 3, 4] {
+            let scan = FileScan {
+                threads,
+                chunk_len: 1000,
+            };
+            assert_eq!(implementation::naive(&l), scan.run(&path).unwrap());
+        }
+        assert_eq!(implementation::naive(&l), least_8_file(&path).unwrap());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn short_and_truncated_files() {
+        let path = write_temp("file_scan_short", &[3, 1, 2]);
+        assert_eq!(vec![1, 2, 3], least_8_file(&path).unwrap());
+        std::fs::write(&path, [0u8; 5]).unwrap();
+        assert_eq!(
+            io::ErrorKind::InvalidData,
+            least_8_file(&path).unwrap_err().kind()
+        );
+        std::fs::remove_file(path).unwrap();
+    }
+}
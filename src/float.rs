@@ -0,0 +1,328 @@
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+
+use crate::error::Least8Error;
+use crate::implementation::{self, CAPACITY};
+
+// what to do with NaNs, which have no place among the smallest values
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum NanPolicy {
+    // skip them
+    #[default]
+    Ignore,
+    // every NaN sorts after +inf, so it is only selected among fewer than 8 other values
+    Largest,
+    // fail with `Least8Error::NanFound` at the first one
+    Error,
+}
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for f32 {}
+    impl Sealed for f64 {}
+}
+
+pub trait Float: Copy + sealed::Sealed {
+    // what every NaN becomes with `NanPolicy::Largest`, the last value in `total_cmp` order
+    const LARGEST_NAN: Self;
+    fn is_nan(self) -> bool;
+    fn total_cmp(&self, other: &Self) -> Ordering;
+}
+
+impl Float for f32 {
+    const LARGEST_NAN: Self = f32::from_bits(0x7fff_ffff);
+
+    fn is_nan(self) -> bool {
+        f32::is_nan(self)
+    }
+
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f32::total_cmp(self, other)
+    }
+}
+
+impl Float for f64 {
+    const LARGEST_NAN: Self = f64::from_bits(0x7fff_ffff_ffff_ffff);
+
+    fn is_nan(self) -> bool {
+        f64::is_nan(self)
+    }
+
+    fn total_cmp(&self, other: &Self) -> Ordering {
+        f64::total_cmp(self, other)
+    }
+}
+
+// the value to select from, `None` to skip it
+fn prepare<T: Float>(value: T, index: usize, nan: NanPolicy) -> Result<Option<T>, Least8Error> {
+    if !value.is_nan() {
+        return Ok(Some(value));
+    }
+    match nan {
+        NanPolicy::Ignore => Ok(None),
+        NanPolicy::Largest => Ok(Some(T::LARGEST_NAN)),
+        NanPolicy::Error => Err(Least8Error::NanFound { index }),
+    }
+}
+
+// `implementation::optimized` in `total_cmp` order, so -0.0 comes before 0.0
+pub fn optimized<T: Float>(l: &[T], nan: NanPolicy) -> Result<Vec<T>, Least8Error> {
+    optimized_from(l, 0, nan)
+}
+
+// `offset` is the index of `l[0]` in the whole input, for `NanFound`
+fn optimized_from<T: Float>(l: &[T], offset: usize, nan: NanPolicy) -> Result<Vec<T>, Least8Error> {
+    let mut ret: Vec<T> = Vec::with_capacity(CAPACITY);
+    for (i, element) in l.iter().enumerate() {
+        let Some(element) = prepare(*element, offset + i, nan)? else {
+            continue;
+        };
+        if ret.len() == CAPACITY && element.total_cmp(&ret[CAPACITY - 1]).is_ge() {
+            continue;
+        }
+        match ret.binary_search_by(|probe| probe.total_cmp(&element)) {
+            Ok(index) | Err(index) => {
+                if ret.len() == CAPACITY {
+                    ret.pop();
+                }
+                ret.insert(index, element);
+            }
+        }
+    }
+    Ok(ret)
+}
+
+// the chunks of `implementation::thread_optimized` on scoped threads, merged by `optimized`
+#[cfg(feature = "std")]
+pub fn thread_optimized<T: Float + Send + Sync>(
+    l: &[T],
+    nan: NanPolicy,
+) -> Result<Vec<T>, Least8Error> {
+    let threads_count = implementation::threads_count(l.len());
+    let mut offset = 0;
+    let chunks = implementation::chunks(l, threads_count).map(|chunk| {
+        offset += chunk.len();
+        (offset - chunk.len(), chunk)
+    });
+    let results = implementation::scoped_workers(chunks, |(offset, chunk)| {
+        optimized_from(chunk, offset, nan)
+    })?;
+    let mut rets = Vec::with_capacity(threads_count * CAPACITY);
+    // in chunk order, so a NaN error reports the first NaN of the input
+    for result in results {
+        rets.extend(result?);
+    }
+    // NaNs left in the partial results are `LARGEST_NAN` already
+    optimized(&rets, NanPolicy::Largest)
+}
+
+// the unsigned order of the keys is the `total_cmp` order of the floats
+fn f32_to_key(value: f32) -> u32 {
+    let bits = value.to_bits();
+    if bits >> 31 == 1 {
+        !bits
+    } else {
+        bits | 1 << 31
+    }
+}
+
+fn key_to_f32(key: u32) -> f32 {
+    f32::from_bits(if key >> 31 == 1 {
+        key & !(1 << 31)
+    } else {
+        !key
+    })
+}
+
+// `implementation::non_cheat_optimized` packing the order-preserving keys of the floats
+pub fn non_cheat_optimized_f32(l: &[f32], nan: NanPolicy) -> Result<Vec<f32>, Least8Error> {
+    let mut nan_found = None;
+    let keys = l
+        .iter()
+        .enumerate()
+        .filter_map(|(i, value)| prepare(*value, i, nan).transpose())
+        .map_while(|value| value.map_err(|err| nan_found = Some(err)).ok())
+        .map(f32_to_key);
+    let least8 = implementation::non_cheat_optimized_keys(keys);
+    if let Some(err) = nan_found {
+        return Err(err);
+    }
+    Ok(least8.iter().map(|key| key_to_f32(*key)).collect())
+}
+
+fn f64_to_key(value: f64) -> u64 {
+    let bits = value.to_bits();
+    if bits >> 63 == 1 {
+        !bits
+    } else {
+        bits | 1 << 63
+    }
+}
+
+fn key_to_f64(key: u64) -> f64 {
+    f64::from_bits(if key >> 63 == 1 {
+        key & !(1 << 63)
+    } else {
+        !key
+    })
+}
+
+// the same with u64 keys in the packed store of `implementation::non_cheat_optimized_u64`
+pub fn non_cheat_optimized_f64(l: &[f64], nan: NanPolicy) -> Result<Vec<f64>, Least8Error> {
+    let mut nan_found = None;
+    let keys = l
+        .iter()
+        .enumerate()
+        .filter_map(|(i, value)| prepare(*value, i, nan).transpose())
+        .map_while(|value| value.map_err(|err| nan_found = Some(err)).ok())
+        .map(f64_to_key);
+    let least8 = implementation::non_cheat_optimized_u64_keys(keys);
+    if let Some(err) = nan_found {
+        return Err(err);
+    }
+    Ok(least8.iter().map(|key| key_to_f64(*key)).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bits(values: &[f32]) -> Vec<u32> {
+        values.iter().map(|value| value.to_bits()).collect()
+    }
+
+    fn expected(l: &[f32]) -> Vec<u32> {
+        let mut sorted: Vec<f32> = l.iter().copied().filter(|value| !value.is_nan()).collect();
+        sorted.sort_by(f32::total_cmp);
+        sorted.truncate(CAPACITY);
+        bits(&sorted)
+    }
+
+    type Strategy = fn(&[f32], NanPolicy) -> Result<Vec<f32>, Least8Error>;
+
+    const STRATEGIES: &[(&str, Strategy)] = &[
+        ("optimized", optimized),
+        #[cfg(feature = "std")]
+        ("thread_optimized", thread_optimized),
+        ("non_cheat_optimized_f32", non_cheat_optimized_f32),
+    ];
+
+    fn sample() -> Vec<f32> {
+        let mut num = 998_244_353_u32;
+        let mut l: Vec<f32> = (0..10_000)
+            .map(|_| {
+                num ^= num << 13;
+                num ^= num >> 17;
+                num ^= num << 5;
+                (num as i32) as f32 / 1e3
+            })
+            .collect();
+        l.extend([
+            f32::NAN,
+            f32::INFINITY,
+            f32::NEG_INFINITY,
+            -0.0,
+            0.0,
+            f32::MIN,
+            f32::MIN_POSITIVE,
+            -f32::NAN,
+        ]);
+        l.rotate_left(5_000);
+        l
+    }
+
+    #[test]
+    fn agrees_with_sorting() {
+        let l = sample();
+        for (name, strategy) in STRATEGIES {
+            for nan in [NanPolicy::Ignore, NanPolicy::Largest] {
+                let got = strategy(&l, nan).unwrap();
+                assert_eq!(expected(&l), bits(&got), "{name} {nan:?}");
+            }
+            assert_eq!(
+                Err(Least8Error::NanFound { index: 5_000 }),
+                strategy(&l, NanPolicy::Error),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn nan_policies() {
+        let l = [3.0, f32::NAN, -0.0, 0.0, -f32::NAN, f32::INFINITY, -1.5];
+        for (name, strategy) in STRATEGIES {
+            assert_eq!(
+                bits(&[-1.5, -0.0, 0.0, 3.0, f32::INFINITY]),
+                bits(&strategy(&l, NanPolicy::Ignore).unwrap()),
+                "{name}"
+            );
+            assert_eq!(
+                bits(&[
+                    -1.5,
+                    -0.0,
+                    0.0,
+                    3.0,
+                    f32::INFINITY,
+                    f32::LARGEST_NAN,
+                    f32::LARGEST_NAN
+                ]),
+                bits(&strategy(&l, NanPolicy::Largest).unwrap()),
+                "{name}"
+            );
+            assert_eq!(
+                Err(Least8Error::NanFound { index: 1 }),
+                strategy(&l, NanPolicy::Error),
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn f64() {
+        let l: Vec<f64> = sample().into_iter().map(f64::from).collect();
+        let mut sorted: Vec<f64> = l.iter().copied().filter(|value| !value.is_nan()).collect();
+        sorted.sort_by(f64::total_cmp);
+        sorted.truncate(CAPACITY);
+        assert_eq!(sorted, optimized(&l, NanPolicy::Ignore).unwrap());
+        #[cfg(feature = "std")]
+        assert_eq!(sorted, thread_optimized(&l, NanPolicy::Ignore).unwrap());
+        assert_eq!(
+            sorted,
+            non_cheat_optimized_f64(&l, NanPolicy::Ignore).unwrap()
+        );
+        assert_eq!(
+            Err(Least8Error::NanFound { index: 5_000 }),
+            non_cheat_optimized_f64(&l, NanPolicy::Error)
+        );
+    }
+
+    #[test]
+    fn keys_keep_order() {
+        let values = [
+            -f32::NAN,
+            f32::NEG_INFINITY,
+            f32::MIN,
+            -1.0,
+            -f32::MIN_POSITIVE,
+            -0.0,
+            0.0,
+            f32::MIN_POSITIVE,
+            1.0,
+            f32::MAX,
+            f32::INFINITY,
+            f32::NAN,
+        ];
+        for pair in values.windows(2) {
+            assert!(f32_to_key(pair[0]) < f32_to_key(pair[1]), "{pair:?}");
+        }
+        for value in values {
+            assert_eq!(value.to_bits(), key_to_f32(f32_to_key(value)).to_bits());
+            let value = f64::from(value);
+            assert_eq!(value.to_bits(), key_to_f64(f64_to_key(value)).to_bits());
+        }
+        for pair in values.windows(2) {
+            let (a, b) = (f64::from(pair[0]), f64::from(pair[1]));
+            assert!(f64_to_key(a) < f64_to_key(b), "{pair:?}");
+        }
+    }
+}
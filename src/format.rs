@@ -0,0 +1,403 @@
+use std::fmt;
+use std::io::{self, BufRead, BufReader, BufWriter, ErrorKind, Read, Write};
+
+// input is decoded through a reusable buffer of this many bytes, never loaded whole
+pub const BUFFER_SIZE: usize = 64 * 1024;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Format {
+    // unsigned numbers separated by whitespace or commas
+    Text,
+    LeU32,
+    BeU32,
+    // values must still fit in `u32`
+    LeU64,
+    // unsigned LEB128
+    Varint,
+    Csv(Column),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Column {
+    // zero-based; a first row that doesn't parse there is taken as the header
+    Index(usize),
+    // looked up in the header row
+    Name(String),
+}
+
+impl Format {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        Ok(match s {
+            "text" => Format::Text,
+            "le-u32" => Format::LeU32,
+            "be-u32" => Format::BeU32,
+            "le-u64" => Format::LeU64,
+            "varint" => Format::Varint,
+            _ => match s.strip_prefix("csv:") {
+                Some("") => return Err("csv format needs a column, e.g. csv:latency".to_owned()),
+                Some(column) => Format::Csv(match column.parse() {
+                    Ok(index) => Column::Index(index),
+                    Err(_) => Column::Name(column.to_owned()),
+                }),
+                None => {
+                    return Err(format!(
+                        "unknown format {s:?}, expected text, le-u32, be-u32, le-u64, varint or csv:<column>"
+                    ))
+                }
+            },
+        })
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Text => f.write_str("text"),
+            Format::LeU32 => f.write_str("le-u32"),
+            Format::BeU32 => f.write_str("be-u32"),
+            Format::LeU64 => f.write_str("le-u64"),
+            Format::Varint => f.write_str("varint"),
+            Format::Csv(Column::Index(index)) => write!(f, "csv:{index}"),
+            Format::Csv(Column::Name(name)) => write!(f, "csv:{name}"),
+        }
+    }
+}
+
+pub fn decode(
+    format: &Format,
+    name: &str,
+    reader: impl Read,
+    f: impl FnMut(u32),
+) -> Result<(), String> {
+    match format {
+        Format::Text => read_text(name, BufReader::with_capacity(BUFFER_SIZE, reader), f),
+        Format::LeU32 => read_fixed::<4>(name, reader, f, |bytes| Ok(u32::from_le_bytes(bytes))),
+        Format::BeU32 => read_fixed::<4>(name, reader, f, |bytes| Ok(u32::from_be_bytes(bytes))),
+        Format::LeU64 => read_fixed::<8>(name, reader, f, |bytes| {
+            let value = u64::from_le_bytes(bytes);
+            u32::try_from(value).map_err(|_| format!("value {value} doesn't fit in u32"))
+        }),
+        Format::Varint => read_varint(name, reader, f),
+        Format::Csv(column) => read_csv(
+            name,
+            BufReader::with_capacity(BUFFER_SIZE, reader),
+            column,
+            f,
+        ),
+    }
+}
+
+// writes `values` so that `decode` with the same format reads them back
+pub fn encode(format: &Format, values: &[u32], out: impl Write) -> io::Result<()> {
+    encode_iter(format, values.iter().copied(), out)
+}
+
+// `encode` for values produced on the fly, never holding more than the write buffer
+pub fn encode_iter(
+    format: &Format,
+    values: impl IntoIterator<Item = u32>,
+    out: impl Write,
+) -> io::Result<()> {
+    let mut out = BufWriter::with_capacity(BUFFER_SIZE, out);
+    match format {
+        Format::Text => {
+            for value in values {
+                writeln!(out, "{value}")?;
+            }
+        }
+        Format::LeU32 => {
+            for value in values {
+                out.write_all(&value.to_le_bytes())?;
+            }
+        }
+        Format::BeU32 => {
+            for value in values {
+                out.write_all(&value.to_be_bytes())?;
+            }
+        }
+        Format::LeU64 => {
+            for value in values {
+                out.write_all(&(value as u64).to_le_bytes())?;
+            }
+        }
+        Format::Varint => {
+            for value in values {
+                let mut value = value;
+                while value >= 0x80 {
+                    out.write_all(&[value as u8 | 0x80])?;
+                    value >>= 7;
+                }
+                out.write_all(&[value as u8])?;
+            }
+        }
+        Format::Csv(column) => {
+            // a single column, preceded by enough empty ones to land at the index
+            let (header, padding) = match column {
+                Column::Index(index) => (
+                    (0..=*index)
+                        .map(|i| format!("column{i}"))
+                        .collect::<Vec<_>>()
+                        .join(","),
+                    ",".repeat(*index),
+                ),
+                Column::Name(name) => (name.clone(), String::new()),
+            };
+            writeln!(out, "{header}")?;
+            for value in values {
+                writeln!(out, "{padding}{value}")?;
+            }
+        }
+    }
+    out.flush()
+}
+
+pub fn read_text(name: &str, reader: impl BufRead, mut f: impl FnMut(u32)) -> Result<(), String> {
+    for (line_no, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| format!("{name}: {err}"))?;
+        for token in line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|token| !token.is_empty())
+        {
+            f(token
+                .parse()
+                .map_err(|_| format!("{name}:{}: invalid number {token:?}", line_no + 1))?);
+        }
+    }
+    Ok(())
+}
+
+// fills `buf` as far as possible, returns the count of bytes read, 0 only at the end of input
+fn fill(name: &str, reader: &mut impl Read, buf: &mut [u8]) -> Result<usize, String> {
+    loop {
+        match reader.read(buf) {
+            Ok(n) => return Ok(n),
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(format!("{name}: {err}")),
+        }
+    }
+}
+
+fn read_fixed<const WIDTH: usize>(
+    name: &str,
+    mut reader: impl Read,
+    mut f: impl FnMut(u32),
+    convert: impl Fn([u8; WIDTH]) -> Result<u32, String>,
+) -> Result<(), String> {
+    let mut buf = vec![0u8; BUFFER_SIZE];
+    // bytes of an item split across two reads
+    let mut carried = 0;
+    let mut offset = 0u64;
+    loop {
+        let n = fill(name, &mut reader, &mut buf[carried..])?;
+        if n == 0 {
+            if carried > 0 {
+                return Err(format!(
+                    "{name}: {carried} trailing bytes, input length is not a multiple of {WIDTH}"
+                ));
+            }
+            return Ok(());
+        }
+        let filled = carried + n;
+        let whole = filled - filled % WIDTH;
+        for chunk in buf[..whole].chunks_exact(WIDTH) {
+            let value = convert(chunk.try_into().unwrap())
+                .map_err(|err| format!("{name}: at byte {offset}: {err}"))?;
+            f(value);
+            offset += WIDTH as u64;
+        }
+        buf.copy_within(whole..filled, 0);
+        carried = filled - whole;
+    }
+}
+
+fn read_varint(name: &str, mut reader: impl Read, mut f: impl FnMut(u32)) -> Result<(), String> {
+    let mut buf = vec![0u8; BUFFER_SIZE];
+    // a value may continue in the next read, so the decoder state outlives the buffer
+    let mut value = 0u64;
+    let mut shift = 0;
+    let mut offset = 0u64;
+    loop {
+        let n = fill(name, &mut reader, &mut buf)?;
+        if n == 0 {
+            if shift > 0 {
+                return Err(format!("{name}: truncated varint at the end of input"));
+            }
+            return Ok(());
+        }
+        for byte in &buf[..n] {
+            value |= ((byte & 0x7f) as u64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                f(u32::try_from(value)
+                    .map_err(|_| format!("{name}: at byte {offset}: varint doesn't fit in u32"))?);
+                value = 0;
+                shift = 0;
+            } else if shift >= 35 {
+                return Err(format!(
+                    "{name}: at byte {offset}: varint doesn't fit in u32"
+                ));
+            }
+            offset += 1;
+        }
+    }
+}
+
+fn read_csv(
+    name: &str,
+    reader: impl BufRead,
+    column: &Column,
+    mut f: impl FnMut(u32),
+) -> Result<(), String> {
+    let mut index = match column {
+        Column::Index(index) => Some(*index),
+        Column::Name(_) => None,
+    };
+    for (line_no, line) in reader.lines().enumerate() {
+        let line = line.map_err(|err| format!("{name}: {err}"))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let fields = split_csv_line(&line);
+        let Some(idx) = index else {
+            let Column::Name(column) = column else {
+                unreachable!()
+            };
+            index = Some(
+                fields
+                    .iter()
+                    .position(|field| field == column)
+                    .ok_or(format!("{name}: no column {column:?} in the header"))?,
+            );
+            continue;
+        };
+        let field = fields
+            .get(idx)
+            .ok_or(format!("{name}:{}: no column {idx}", line_no + 1))?;
+        match field.trim().parse() {
+            Ok(value) => f(value),
+            Err(_) if line_no == 0 && matches!(column, Column::Index(_)) => {}
+            Err(_) => return Err(format!("{name}:{}: invalid number {field:?}", line_no + 1)),
+        }
+    }
+    Ok(())
+}
+
+// splits on commas outside double quotes, unquoting fields; `""` inside quotes is a quote
+fn split_csv_line(line: &str) -> Vec<String> {
+    let mut fields = vec![];
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(std::mem::take(&mut field)),
+            c => field.push(c),
+        }
+    }
+    fields.push(field);
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(format: &str, input: &[u8]) -> Result<Vec<u32>, String> {
+        let mut values = vec![];
+        decode(&Format::parse(format)?, "input", input, |v| values.push(v))?;
+        Ok(values)
+    }
+
+    #[test]
+    fn read_text_tokens() {
+        let mut values = vec![];
+        read_text("input", "1 2,3\n\n  4\t5 ,6\n".as_bytes(), |v| {
+            values.push(v)
+        })
+        .unwrap();
+        assert_eq!(vec![1, 2, 3, 4, 5, 6], values);
+        assert_eq!(
+            Err("input:2: invalid number \"x\"".to_owned()),
+            read_text("input", "1\n2 x\n".as_bytes(), |_| {})
+        );
+    }
+
+    #[test]
+    fn encode_round_trip() {
+        let values = [0, 1, 127, 128, 300, 1 << 21, u32::MAX];
+        for format in [
+            "text",
+            "le-u32",
+            "be-u32",
+            "le-u64",
+            "varint",
+            "csv:latency",
+            "csv:2",
+        ] {
+            let mut encoded = vec![];
+            encode(&Format::parse(format).unwrap(), &values, &mut encoded).unwrap();
+            assert_eq!(
+                Ok(values.to_vec()),
+                decode_all(format, &encoded),
+                "{format}"
+            );
+        }
+    }
+
+    #[test]
+    fn binary_formats() {
+        let values = [0, 1, 300, u32::MAX];
+        let le: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let be: Vec<u8> = values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        let le64: Vec<u8> = values
+            .iter()
+            .flat_map(|v| (*v as u64).to_le_bytes())
+            .collect();
+        let varint = [0x00, 0x01, 0xac, 0x02, 0xff, 0xff, 0xff, 0xff, 0x0f];
+        assert_eq!(Ok(values.to_vec()), decode_all("le-u32", &le));
+        assert_eq!(Ok(values.to_vec()), decode_all("be-u32", &be));
+        assert_eq!(Ok(values.to_vec()), decode_all("le-u64", &le64));
+        assert_eq!(Ok(values.to_vec()), decode_all("varint", &varint));
+
+        assert!(decode_all("le-u32", &le[..5]).is_err());
+        assert!(decode_all("le-u64", &u64::MAX.to_le_bytes()).is_err());
+        assert!(decode_all("varint", &[0x80]).is_err());
+        assert!(decode_all("varint", &[0xff, 0xff, 0xff, 0xff, 0x1f]).is_err());
+    }
+
+    #[test]
+    fn items_split_across_reads() {
+        // a reader returning at most 3 bytes per read splits every item
+        struct Trickle<'a>(&'a [u8]);
+        impl Read for Trickle<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let n = self.0.len().min(buf.len()).min(3);
+                buf[..n].copy_from_slice(&self.0[..n]);
+                self.0 = &self.0[n..];
+                Ok(n)
+            }
+        }
+        let values: Vec<u32> = (0..1000).map(|i| i * 7919).collect();
+        let le: Vec<u8> = values.iter().flat_map(|v| v.to_le_bytes()).collect();
+        let mut decoded = vec![];
+        decode(&Format::LeU32, "input", Trickle(&le), |v| decoded.push(v)).unwrap();
+        assert_eq!(values, decoded);
+    }
+
+    #[test]
+    fn csv_columns() {
+        let csv = b"endpoint,latency\n/a,5\n\"/b,c\",3\n";
+        assert_eq!(Ok(vec![5, 3]), decode_all("csv:latency", csv));
+        assert_eq!(Ok(vec![5, 3]), decode_all("csv:1", csv));
+        assert_eq!(Ok(vec![5, 3]), decode_all("csv:1", &csv[17..]));
+        assert!(decode_all("csv:missing", csv).is_err());
+        assert!(decode_all("csv:0", csv).is_err());
+        assert!(Format::parse("csv:").is_err());
+        assert!(Format::parse("bogus").is_err());
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use crate::error::Least8Error;
+use crate::implementation::{self, Least8, MAX_THREADS_COUNT};
+
+// the 8 smallest values of every key, e.g. latencies per endpoint, in one pass
+pub fn least_8_grouped<K: Hash + Eq + Clone>(l: &[(K, u32)]) -> HashMap<K, Vec<u32>> {
+    let mut grouped = GroupedLeast8::new();
+    grouped.push_many(l);
+    grouped.into_map()
+}
+
+// streaming form of `least_8_grouped`: one insertion buffer with its rightmost guard per key,
+// kept off the heap inside the map
+#[derive(Clone, Debug)]
+pub struct GroupedLeast8<K> {
+    groups: HashMap<K, Group>,
+    max_groups: Option<usize>,
+    dropped: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Group {
+    // how many keys were kept before this one, `merge` admits new keys in this order
+    seen: usize,
+    // every value of the key, so dropping the group in `merge` counts what `push` would have
+    pushed: u64,
+    values: Least8,
+}
+
+impl<K: Hash + Eq> Default for GroupedLeast8<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Hash + Eq> GroupedLeast8<K> {
+    pub fn new() -> Self {
+        Self {
+            groups: HashMap::new(),
+            max_groups: None,
+            dropped: 0,
+        }
+    }
+
+    // once `max_groups` keys are kept, values of any other key are dropped
+    pub fn max_groups(mut self, max_groups: usize) -> Self {
+        self.max_groups = Some(max_groups);
+        self
+    }
+
+    // count of keys kept
+    pub fn len(&self) -> usize {
+        self.groups.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    // count of values dropped because of `max_groups`
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    #[inline]
+    pub fn push(&mut self, key: K, value: u32) {
+        if let Some(group) = self.groups.get_mut(&key) {
+            group.pushed += 1;
+            group.values.insert(value);
+            return;
+        }
+        if self.is_full() {
+            self.dropped += 1;
+            return;
+        }
+        let mut values = Least8::default();
+        values.insert(value);
+        let seen = self.groups.len();
+        self.groups.insert(
+            key,
+            Group {
+                seen,
+                pushed: 1,
+                values,
+            },
+        );
+    }
+
+    fn is_full(&self) -> bool {
+        self.max_groups
+            .is_some_and(|max_groups| self.groups.len() >= max_groups)
+    }
+
+    pub fn push_many(&mut self, l: &[(K, u32)])
+    where
+        K: Clone,
+    {
+        for (key, value) in l {
+            self.push(key.clone(), *value);
+        }
+    }
+
+    // new keys of `other` count against `max_groups` like pushed ones, in the order `other` first
+    // saw them, so the same keys are kept whatever the hashing
+    pub fn merge(&mut self, other: GroupedLeast8<K>) {
+        self.dropped += other.dropped;
+        let mut groups: Vec<_> = other.groups.into_iter().collect();
+        groups.sort_unstable_by_key(|(_, group)| group.seen);
+        for (key, group) in groups {
+            if let Some(into) = self.groups.get_mut(&key) {
+                into.pushed += group.pushed;
+                for value in group.values.iter() {
+                    into.values.insert(*value);
+                }
+            } else if self.is_full() {
+                self.dropped += group.pushed;
+            } else {
+                let seen = self.groups.len();
+                self.groups.insert(key, Group { seen, ..group });
+            }
+        }
+    }
+
+    // `push_many` on scoped threads, one chunk of `l` each, with the per-thread maps merged in
+    // chunk order, so the result is the same as pushing `l` on this thread; only the merge applies
+    // `max_groups`, so it does not bound memory here: every worker keeps every key of its chunk
+    pub fn thread_push_many(&mut self, l: &[(K, u32)]) -> Result<(), Least8Error>
+    where
+        K: Clone + Send + Sync,
+    {
+        // one chunk per thread, a key may show up in every chunk anyway
+        let threads_count = l.len().clamp(1, MAX_THREADS_COUNT);
+        let results =
+            implementation::scoped_workers(implementation::chunks(l, threads_count), |chunk| {
+                let mut grouped = GroupedLeast8::new();
+                grouped.push_many(chunk);
+                grouped
+            })?;
+        for grouped in results {
+            self.merge(grouped);
+        }
+        Ok(())
+    }
+
+    // ascending
+    pub fn get(&self, key: &K) -> Option<&[u32]> {
+        self.groups.get(key).map(|group| group.values.as_slice())
+    }
+
+    pub fn into_map(self) -> HashMap<K, Vec<u32>> {
+        self.groups
+            .into_iter()
+            .map(|(key, group)| (key, group.values.to_vec()))
+            .collect()
+    }
+}
+
+// `least_8_grouped` on scoped threads, see `GroupedLeast8::thread_push_many`
+pub fn thread_least_8_grouped<K: Hash + Eq + Clone + Send + Sync>(
+    l: &[(K, u32)],
+) -> Result<HashMap<K, Vec<u32>>, Least8Error> {
+    let mut grouped = GroupedLeast8::new();
+    grouped.thread_push_many(l)?;
+    Ok(grouped.into_map())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{implementation, make_list};
+
+    // latencies of 37 endpoints, some with fewer than 8 samples
+    fn sample() -> Vec<(String, u32)> {
+        make_list()[..20_000]
+            .iter()
+            .enumerate()
+            .map(|(i, value)| {
+                let endpoint = if i % 1_000 == 0 { 36 } else { value % 36 };
+                (format!("/endpoint/{endpoint}"), value >> 8)
+            })
+            .collect()
+    }
+
+    fn expected(l: &[(String, u32)]) -> HashMap<String, Vec<u32>> {
+        let mut groups: HashMap<String, Vec<u32>> = HashMap::new();
+        for (key, value) in l {
+            groups.entry(key.clone()).or_default().push(*value);
+        }
+        groups
+            .into_iter()
+            .map(|(key, values)| (key, implementation::naive_array(&values).to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn same_as_per_group_naive() {
+        let l = sample();
+        let expected = expected(&l);
+        assert_eq!(37, expected.len());
+        assert_eq!(expected, least_8_grouped(&l));
+        assert_eq!(expected, thread_least_8_grouped(&l).unwrap());
+        assert_eq!(HashMap::new(), thread_least_8_grouped::<u8>(&[]).unwrap());
+    }
+
+    #[test]
+    fn max_groups() {
+        let l = [(1, 5), (2, 7), (1, 3), (3, 1), (2, 6), (3, 2), (4, 0)];
+        let mut grouped = GroupedLeast8::new().max_groups(2);
+        grouped.push_many(&l);
+        assert_eq!(2, grouped.len());
+        assert_eq!(3, grouped.dropped());
+        assert_eq!(Some(&[3, 5][..]), grouped.get(&1));
+        assert_eq!(Some(&[6, 7][..]), grouped.get(&2));
+        assert_eq!(None, grouped.get(&3));
+
+        let mut other = GroupedLeast8::new();
+        other.push_many(&[(2, 1), (5, 9), (5, 8)]);
+        grouped.merge(other);
+        assert_eq!(Some(&[1, 6, 7][..]), grouped.get(&2));
+        assert_eq!(5, grouped.dropped());
+        assert_eq!(2, grouped.into_map().len());
+    }
+
+    #[test]
+    fn merge_over_max_groups() {
+        // enough keys that hashing order is not first-seen order
+        let l: Vec<(u32, u32)> = (0..100).rev().map(|key| (key, key)).collect();
+        let mut other = GroupedLeast8::new();
+        other.push_many(&l);
+        let mut grouped = GroupedLeast8::new().max_groups(3);
+        grouped.push(1_000, 0);
+        grouped.merge(other);
+        assert_eq!(3, grouped.len());
+        assert_eq!(98, grouped.dropped());
+        for key in [1_000, 99, 98] {
+            assert!(grouped.get(&key).is_some(), "{key}");
+        }
+    }
+
+    #[test]
+    fn thread_push_many_over_max_groups() {
+        let l = sample();
+        let mut expected = GroupedLeast8::new().max_groups(10);
+        expected.push_many(&l);
+        let mut grouped = GroupedLeast8::new().max_groups(10);
+        grouped.thread_push_many(&l).unwrap();
+        assert_eq!(expected.dropped(), grouped.dropped());
+        assert_eq!(expected.into_map(), grouped.into_map());
+    }
+}
@@ -0,0 +1,312 @@
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::thread;
+use std::time::Instant;
+
+use crate::format::{self, Format};
+use crate::implementation::{self, STRATEGIES};
+use crate::json::Value;
+use crate::output::Report;
+use crate::query::Query;
+
+// requests with longer heads are refused
+const MAX_HEAD_LEN: usize = 16 * 1024;
+// bodies are streamed and not limited, but with `strategy=` the values are collected first, so
+// longer bodies are refused then
+pub const MAX_COLLECTED_BODY_LEN: u64 = 256 << 20;
+// larger `k`s are refused, the streaming accumulator keeps up to `k` values
+pub const MAX_K: usize = 1 << 20;
+
+// POST /least?k=8&strategy=optimized&largest&distinct&indices&format=le-u32
+//   body: numbers in `format`; text (whitespace or newline separated) by default,
+//   le-u32 if the content type is application/octet-stream
+// GET /strategies
+// every request gets a JSON response and the connection is closed
+pub struct HttpServer {
+    listener: TcpListener,
+}
+
+impl HttpServer {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // serves every connection on its own thread, forever
+    pub fn run(self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            thread::spawn(move || {
+                // a client going away is not the server's problem
+                let _ = handle_connection(stream);
+            });
+        }
+        Ok(())
+    }
+}
+
+struct Response {
+    status: u16,
+    body: Value,
+}
+
+impl Response {
+    fn ok(body: Value) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            body: Value::object([("error", Value::from(message.into()))]),
+        }
+    }
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        411 => "Length Required",
+        413 => "Content Too Large",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
+
+fn handle_connection(stream: TcpStream) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    let mut reader = BufReader::new(stream);
+    let response = handle_request(&mut reader)?;
+    let body = format!("{}\n", response.body);
+    write!(
+        writer,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        response.status,
+        reason(response.status),
+        body.len(),
+    )?;
+    writer.flush()
+}
+
+struct Head {
+    method: String,
+    path: String,
+    query: Vec<(String, String)>,
+    content_length: Option<u64>,
+    content_type: Option<String>,
+}
+
+fn read_head(reader: &mut impl BufRead) -> io::Result<Result<Head, Response>> {
+    let mut lines = vec![];
+    let mut len = 0;
+    loop {
+        let mut line = String::new();
+        // one byte over the limit is enough to tell, a line without `\n` must not be buffered whole
+        let limit = (MAX_HEAD_LEN - len + 1) as u64;
+        let read = reader.by_ref().take(limit).read_line(&mut line)?;
+        if read == 0 {
+            return Ok(Err(Response::error(400, "incomplete request head")));
+        }
+        len += read;
+        if len > MAX_HEAD_LEN {
+            return Ok(Err(Response::error(431, "request head too large")));
+        }
+        let line = line.trim_end_matches(['\r', '\n']).to_owned();
+        if line.is_empty() {
+            break;
+        }
+        lines.push(line);
+    }
+    let mut request_line = lines
+        .first()
+        .map(|line| line.split(' '))
+        .into_iter()
+        .flatten();
+    let (Some(method), Some(target), Some(_version)) = (
+        request_line.next(),
+        request_line.next(),
+        request_line.next(),
+    ) else {
+        return Ok(Err(Response::error(400, "malformed request line")));
+    };
+    let (path, query) = target.split_once('?').unwrap_or((target, ""));
+    let mut head = Head {
+        method: method.to_owned(),
+        path: path.to_owned(),
+        query: query
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (percent_decode(name), percent_decode(value))
+            })
+            .collect(),
+        content_length: None,
+        content_type: None,
+    };
+    for line in &lines[1..] {
+        let Some((name, value)) = line.split_once(':') else {
+            return Ok(Err(Response::error(
+                400,
+                format!("malformed header {line:?}"),
+            )));
+        };
+        let value = value.trim();
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => match value.parse() {
+                Ok(len) => head.content_length = Some(len),
+                Err(_) => return Ok(Err(Response::error(400, "invalid Content-Length"))),
+            },
+            "content-type" => head.content_type = Some(value.to_ascii_lowercase()),
+            "transfer-encoding" => {
+                return Ok(Err(Response::error(411, "send a Content-Length instead")))
+            }
+            _ => {}
+        }
+    }
+    Ok(Ok(head))
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut ret = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'+' => ret.push(b' '),
+            b'%' if i + 2 < bytes.len() => {
+                match std::str::from_utf8(&bytes[i + 1..i + 3])
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                {
+                    Some(byte) => {
+                        ret.push(byte);
+                        i += 2;
+                    }
+                    None => ret.push(b'%'),
+                }
+            }
+            byte => ret.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&ret).into_owned()
+}
+
+fn handle_request(reader: &mut impl BufRead) -> io::Result<Response> {
+    let head = match read_head(reader)? {
+        Ok(head) => head,
+        Err(response) => return Ok(response),
+    };
+    let mut body = reader.take(head.content_length.unwrap_or(0));
+    let response = match (head.method.as_str(), head.path.as_str()) {
+        ("GET", "/strategies") => strategies(),
+        ("POST", "/least") => match head.content_length {
+            Some(_) => least(&head, &mut body),
+            None => Response::error(411, "POST /least needs a Content-Length"),
+        },
+        (_, "/strategies" | "/least") => Response::error(405, "method not allowed"),
+        _ => Response::error(404, format!("no route for {}", head.path)),
+    };
+    // closing with unread data would reset the connection before the client reads the reply
+    io::copy(&mut body, &mut io::sink())?;
+    Ok(response)
+}
+
+fn strategies() -> Response {
+    let mut strategies = vec![Value::object([
+        ("name", Value::from("stream")),
+        ("exact", true.into()),
+    ])];
+    strategies.extend(STRATEGIES.iter().map(|strategy| {
+        Value::object([
+            ("name", strategy.name.into()),
+            ("exact", strategy.exact.into()),
+        ])
+    }));
+    Response::ok(Value::object([
+        ("default", Value::from("stream")),
+        ("strategies", Value::Array(strategies)),
+    ]))
+}
+
+fn least(head: &Head, body: impl Read) -> Response {
+    let start = Instant::now();
+    let mut query = Query::default();
+    let mut format = match head.content_type.as_deref() {
+        Some("application/octet-stream") => Format::LeU32,
+        _ => Format::Text,
+    };
+    for (name, value) in &head.query {
+        let flag = || matches!(value.as_str(), "" | "1" | "true");
+        match name.as_str() {
+            "k" => match value.parse() {
+                Ok(k) if k <= MAX_K => query.k = k,
+                Ok(_) => return Response::error(400, format!("k must not exceed {MAX_K}")),
+                Err(_) => return Response::error(400, format!("invalid k {value:?}")),
+            },
+            "strategy" if value == "stream" => query.strategy = None,
+            "strategy" => match implementation::strategy(value) {
+                Some(strategy) => query.strategy = Some(strategy),
+                None => return Response::error(400, format!("unknown strategy {value:?}")),
+            },
+            "largest" => query.largest = flag(),
+            "distinct" => query.distinct = flag(),
+            "indices" => query.indices = flag(),
+            "format" => match Format::parse(value) {
+                Ok(f) => format = f,
+                Err(err) => return Response::error(400, err),
+            },
+            _ => return Response::error(400, format!("unknown parameter {name:?}")),
+        }
+    }
+    if query.strategy.is_some() && head.content_length > Some(MAX_COLLECTED_BODY_LEN) {
+        return Response::error(
+            413,
+            format!("bodies for a strategy must not exceed {MAX_COLLECTED_BODY_LEN} bytes"),
+        );
+    }
+    let selection = match query.strategy {
+        Some(_) => {
+            let mut l = vec![];
+            format::decode(&format, "body", body, |value| l.push(value)).and_then(|_| query.run(&l))
+        }
+        None => {
+            let mut accu = query.accumulator();
+            format::decode(&format, "body", body, |value| accu.push(value))
+                .map(|_| query.selection(&accu))
+        }
+    };
+    match selection {
+        Ok(selection) => Response::ok(
+            Report {
+                selection: &selection,
+                strategy: query.strategy_name(),
+                exact: query.is_exact(),
+                elapsed: start.elapsed(),
+            }
+            .to_json(),
+        ),
+        Err(err) => Response::error(400, err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn percent_decoding() {
+        assert_eq!("a b/c", percent_decode("a+b%2Fc"));
+        assert_eq!("100%", percent_decode("100%"));
+        assert_eq!("%zz", percent_decode("%zz"));
+    }
+}
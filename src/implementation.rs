@@ -1,50 +1,332 @@
-const CAPACITY: usize = 8;
+use core::ops::{BitAnd, BitAndAssign, BitOr, BitOrAssign, Deref, Not};
 
+use crate::error::Least8Error;
+
+#[cfg(feature = "alloc")]
+use alloc::{borrow::ToOwned, vec::Vec};
+
+pub const CAPACITY: usize = 8;
+
+// indexing of the packed stores, bounds checked with the `safe` feature
+#[cfg(not(feature = "safe"))]
+#[inline(always)]
+fn at<T>(slice: &[T], i: usize) -> &T {
+    debug_assert!(i < slice.len());
+    unsafe { slice.get_unchecked(i) }
+}
+
+#[cfg(not(feature = "safe"))]
+#[inline(always)]
+fn at_mut<T>(slice: &mut [T], i: usize) -> &mut T {
+    debug_assert!(i < slice.len());
+    unsafe { slice.get_unchecked_mut(i) }
+}
+
+#[cfg(feature = "safe")]
+#[inline(always)]
+fn at<T>(slice: &[T], i: usize) -> &T {
+    &slice[i]
+}
+
+#[cfg(feature = "safe")]
+#[inline(always)]
+fn at_mut<T>(slice: &mut [T], i: usize) -> &mut T {
+    &mut slice[i]
+}
+
+// the `CAPACITY` smallest values (fewer for shorter inputs), ascending, kept off the heap
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Least8<T = u32> {
+    values: [T; CAPACITY],
+    len: usize,
+}
+
+impl<T: Copy + Ord> Least8<T> {
+    pub fn as_slice(&self) -> &[T] {
+        &self.values[..self.len]
+    }
+
+    // unused tail slots are 0
+    pub fn into_array(self) -> [T; CAPACITY] {
+        self.values
+    }
+
+    // keeps `value` if it is among the CAPACITY smallest so far, the rightmost value falls off
+    // when full; `false` if `value` didn't get in
+    #[inline]
+    pub(crate) fn insert(&mut self, value: T) -> bool {
+        if self.len == CAPACITY && value >= self.values[CAPACITY - 1] {
+            return false;
+        }
+        match self.values[..self.len].binary_search(&value) {
+            Ok(index) | Err(index) => {
+                if self.len < CAPACITY {
+                    self.len += 1;
+                }
+                self.values.copy_within(index..self.len - 1, index + 1);
+                self.values[index] = value;
+            }
+        }
+        true
+    }
+
+    // for the `try_` functions: all CAPACITY values or the error
+    fn into_full(self) -> Result<[T; CAPACITY], Least8Error> {
+        if self.len < CAPACITY {
+            return Err(Least8Error::NotEnoughElements { found: self.len });
+        }
+        Ok(self.values)
+    }
+
+    fn write_into(self, out: &mut [T; CAPACITY]) -> usize {
+        *out = self.values;
+        self.len
+    }
+}
+
+impl<T: Copy + Ord> Deref for Least8<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        self.as_slice()
+    }
+}
+
+#[cfg(feature = "alloc")]
+pub struct Strategy {
+    pub name: &'static str,
+    pub func: fn(&[u32]) -> Vec<u32>,
+    // the same without allocating for the values, returns how many values were written;
+    // only `thread_optimized` touches the heap, for what std needs to spawn its threads
+    pub into: fn(&[u32], &mut [u32; CAPACITY]) -> usize,
+    // `false` if the strategy drops values it can't pack (see `cheat_optimized`)
+    pub exact: bool,
+}
+
+#[cfg(feature = "alloc")]
+pub const STRATEGIES: &[Strategy] = &[
+    Strategy {
+        name: "naive",
+        func: naive,
+        into: naive_into,
+        exact: true,
+    },
+    Strategy {
+        name: "optimized",
+        func: optimized,
+        into: optimized_into,
+        exact: true,
+    },
+    #[cfg(feature = "std")]
+    Strategy {
+        name: "thread_optimized",
+        func: thread_optimized,
+        into: thread_optimized_into,
+        exact: true,
+    },
+    Strategy {
+        name: "cheat_optimized",
+        func: cheat_optimized,
+        into: cheat_optimized_into,
+        exact: false,
+    },
+    Strategy {
+        name: "less_cheat_optimized",
+        func: less_cheat_optimized,
+        into: less_cheat_optimized_into,
+        exact: false,
+    },
+    Strategy {
+        name: "non_cheat_optimized",
+        func: non_cheat_optimized,
+        into: non_cheat_optimized_into,
+        exact: true,
+    },
+];
+
+// the same without the rightmost guard, for the README's benchmark of the strategies before it
+#[cfg(feature = "std")]
+pub const UNGUARDED_STRATEGIES: &[Strategy] = &[
+    Strategy {
+        name: "naive",
+        func: naive,
+        into: naive_into,
+        exact: true,
+    },
+    Strategy {
+        name: "optimized",
+        func: |l| optimized_least8::<false>(l).to_vec(),
+        into: |l, out| optimized_least8::<false>(l).write_into(out),
+        exact: true,
+    },
+    Strategy {
+        name: "thread_optimized",
+        func: |l| {
+            thread_optimized_least8::<false>(l)
+                .unwrap_or_else(|err| panic!("{err}"))
+                .to_vec()
+        },
+        into: |l, out| {
+            thread_optimized_least8::<false>(l)
+                .unwrap_or_else(|err| panic!("{err}"))
+                .write_into(out)
+        },
+        exact: true,
+    },
+    Strategy {
+        name: "cheat_optimized",
+        func: |l| cheat_optimized_least8::<false>(l).to_vec(),
+        into: |l, out| cheat_optimized_least8::<false>(l).write_into(out),
+        exact: false,
+    },
+    Strategy {
+        name: "less_cheat_optimized",
+        func: |l| unguarded_less_cheat_optimized(l).to_vec(),
+        into: |l, out| unguarded_less_cheat_optimized(l).write_into(out),
+        exact: false,
+    },
+    Strategy {
+        name: "non_cheat_optimized",
+        func: |l| packed_least8::<u64, 4, u32, false>(l.iter().copied()).to_vec(),
+        into: |l, out| packed_least8::<u64, 4, u32, false>(l.iter().copied()).write_into(out),
+        exact: true,
+    },
+];
+
+#[cfg(feature = "std")]
+fn unguarded_less_cheat_optimized(l: &[u32]) -> Least8 {
+    let keys = l
+        .iter()
+        .filter(|i| **i <= u16::MAX as u32)
+        .map(|i| *i as u16);
+    widen(packed_least8::<u64, 2, u16, false>(keys))
+}
+
+#[cfg(feature = "alloc")]
+pub fn strategy(name: &str) -> Option<&'static Strategy> {
+    STRATEGIES.iter().find(|strategy| strategy.name == name)
+}
+
+#[cfg(feature = "alloc")]
 pub fn naive(l: &[u32]) -> Vec<u32> {
     let mut ll = l.to_owned();
     ll.sort();
     ll[0..CAPACITY].to_vec()
 }
 
+// without a copy to sort: scan for the next smallest value until CAPACITY values are found
+pub fn naive_array(l: &[u32]) -> Least8 {
+    let mut ret = Least8::default();
+    let mut prev = None;
+    while ret.len < CAPACITY {
+        let Some(value) = l
+            .iter()
+            .copied()
+            .filter(|value| prev.is_none_or(|prev| *value > prev))
+            .min()
+        else {
+            break;
+        };
+        let count = l.iter().filter(|other| **other == value).count();
+        for _ in 0..count.min(CAPACITY - ret.len) {
+            ret.values[ret.len] = value;
+            ret.len += 1;
+        }
+        prev = Some(value);
+    }
+    ret
+}
+
+pub fn naive_into(l: &[u32], out: &mut [u32; CAPACITY]) -> usize {
+    naive_array(l).write_into(out)
+}
+
+pub fn try_naive(l: &[u32]) -> Result<[u32; CAPACITY], Least8Error> {
+    naive_array(l).into_full()
+}
+
 // form the result in one pass through the original array, accumulating the smallest values in the sorted result array
-pub fn optimized(l: &[u32]) -> Vec<u32> {
-    let mut ret = Vec::with_capacity(CAPACITY);
+pub fn optimized_array(l: &[u32]) -> Least8 {
+    optimized_least8::<true>(l)
+}
+
+// `GUARD` is the rightmost guard, off only for the README's benchmark of the strategies before it
+fn optimized_least8<const GUARD: bool>(l: &[u32]) -> Least8 {
+    let mut ret = Least8::default();
     let mut right_value = 0;
     for element in l {
-        if ret.len() == CAPACITY && *element >= right_value {
+        if GUARD && ret.len == CAPACITY && *element >= right_value {
             continue;
         }
-        match ret.binary_search(element) {
+        match ret.values[..ret.len].binary_search(element) {
             Ok(index) | Err(index) => {
-                let need_insert = if ret.len() < CAPACITY {
-                    Some(false)
-                } else if index < CAPACITY {
-                    Some(true)
-                } else {
-                    None
-                };
-                if let Some(need_truncate) = need_insert {
-                    if need_truncate {
-                        ret.truncate(CAPACITY - 1);
-                    }
-                    ret.insert(index, *element);
-                    right_value = if index == ret.len() - 1 {
-                        *element
-                    } else {
-                        ret[ret.len() - 1]
-                    };
+                if ret.len < CAPACITY {
+                    ret.len += 1;
+                } else if index == CAPACITY {
+                    continue;
                 }
+                // the rightmost value falls off when the result is full
+                ret.values.copy_within(index..ret.len - 1, index + 1);
+                ret.values[index] = *element;
+                right_value = ret.values[ret.len - 1];
             }
         }
     }
     ret
 }
 
+#[cfg(feature = "alloc")]
+pub fn optimized(l: &[u32]) -> Vec<u32> {
+    optimized_array(l).to_vec()
+}
+
+pub fn optimized_into(l: &[u32], out: &mut [u32; CAPACITY]) -> usize {
+    optimized_array(l).write_into(out)
+}
+
+pub fn try_optimized(l: &[u32]) -> Result<[u32; CAPACITY], Least8Error> {
+    optimized_array(l).into_full()
+}
+
+// the 8 smallest values, or why there aren't
+pub fn try_least_8(l: &[u32]) -> Result<[u32; CAPACITY], Least8Error> {
+    try_optimized(l)
+}
+
+#[cfg(feature = "alloc")]
 pub fn cheat_optimized(l: &[u32]) -> Vec<u32> {
+    cheat_optimized_array(l).to_vec()
+}
+
+pub fn cheat_optimized_into(l: &[u32], out: &mut [u32; CAPACITY]) -> usize {
+    cheat_optimized_array(l).write_into(out)
+}
+
+pub fn try_cheat_optimized(l: &[u32]) -> Result<[u32; CAPACITY], Least8Error> {
+    cheat_optimized_array(l).into_full()
+}
+
+pub fn cheat_optimized_array(l: &[u32]) -> Least8 {
+    cheat_optimized_least8::<true>(l)
+}
+
+fn cheat_optimized_least8<const GUARD: bool>(l: &[u32]) -> Least8 {
     type AccuType = u64;
     type AccuItemType = u8;
     const ACCU_ITEM_TYPE_BITS_LEN: usize = 8;
     // ===================
+    #[derive(Clone, Copy)]
+    struct Mask {
+        tail: AccuType,
+        head: AccuType,
+    }
+    let mut masks = [Mask { tail: 0, head: 0 }; CAPACITY];
+    let mut tail = AccuType::MAX;
+    for mask in masks.iter_mut() {
+        *mask = Mask { tail, head: !tail };
+        tail <<= ACCU_ITEM_TYPE_BITS_LEN;
+    }
+    // ===================
     let mut accu: AccuType = 0;
     let mut len = 0usize;
     let mut right_value: AccuItemType = 0;
@@ -53,7 +335,7 @@ pub fn cheat_optimized(l: &[u32]) -> Vec<u32> {
         .filter(|i| **i <= AccuItemType::MAX as u32)
         .map(|i| *i as AccuItemType)
     {
-        if len == CAPACITY && element >= right_value {
+        if GUARD && len == CAPACITY && element >= right_value {
             continue;
         }
         let mut size = len;
@@ -66,7 +348,7 @@ pub fn cheat_optimized(l: &[u32]) -> Vec<u32> {
             let mid = left + size / 2;
             let mid_value = (accu >> (mid * ACCU_ITEM_TYPE_BITS_LEN)) as AccuItemType;
             let cmp = element.cmp(&mid_value);
-            use std::cmp::Ordering::*;
+            use core::cmp::Ordering::*;
             match cmp {
                 Greater => left = mid + 1,
                 Less => right = mid,
@@ -74,17 +356,6 @@ pub fn cheat_optimized(l: &[u32]) -> Vec<u32> {
             }
             size = right - left;
         };
-        struct Mask {
-            tail: AccuType,
-            head: AccuType,
-        }
-        let mut masks = Vec::with_capacity(CAPACITY);
-        let mut tail = AccuType::MAX;
-        masks.push(Mask { tail, head: !tail });
-        for _ in 1..CAPACITY {
-            tail <<= ACCU_ITEM_TYPE_BITS_LEN;
-            masks.push(Mask { tail, head: !tail });
-        }
         let need_set = if len < CAPACITY {
             let need_space = index < len;
             len += 1;
@@ -96,10 +367,13 @@ pub fn cheat_optimized(l: &[u32]) -> Vec<u32> {
         };
         if let Some(need_space) = need_set {
             if need_space {
-                let mask = unsafe { masks.get_unchecked(index) };
+                let mask = at(&masks, index);
                 accu = ((accu & mask.tail) << ACCU_ITEM_TYPE_BITS_LEN) | (accu & mask.head);
             }
-            accu |= (element as AccuType) << (index * ACCU_ITEM_TYPE_BITS_LEN);
+            let shift = index * ACCU_ITEM_TYPE_BITS_LEN;
+            // the rightmost item is overwritten in place when the accu is full, so clear it first
+            accu &= !((AccuItemType::MAX as AccuType) << shift);
+            accu |= (element as AccuType) << shift;
             right_value = if index == len - 1 {
                 element
             } else {
@@ -107,241 +381,253 @@ pub fn cheat_optimized(l: &[u32]) -> Vec<u32> {
             };
         }
     }
-    let mut ret = Vec::with_capacity(CAPACITY);
+    let mut ret = Least8 {
+        len,
+        ..Least8::default()
+    };
     let mut mask = AccuItemType::MAX as AccuType;
-    for i in 0..len {
-        ret.push(((accu & mask) >> (i * ACCU_ITEM_TYPE_BITS_LEN)) as u32);
+    for (i, value) in ret.values.iter_mut().enumerate().take(len) {
+        *value = ((accu & mask) >> (i * ACCU_ITEM_TYPE_BITS_LEN)) as u32;
         mask <<= ACCU_ITEM_TYPE_BITS_LEN;
     }
     ret
 }
 
+#[cfg(feature = "alloc")]
 pub fn less_cheat_optimized(l: &[u32]) -> Vec<u32> {
-    type AccuType = u64;
-    const ACCU_TYPE_BITS_LEN: usize = 64;
-    type AccuItemType = u16;
-    const ACCU_ITEM_TYPE_BITS_LEN: usize = 16;
-    // ===================
-    type StoreInner = [AccuType; Store::LEN];
-    #[derive(Clone, Copy)]
-    struct Store(StoreInner);
-    impl Store {
-        const LEN: usize = (ACCU_ITEM_TYPE_BITS_LEN * CAPACITY) / ACCU_TYPE_BITS_LEN;
-        const ITEMS_PER_SLOT: usize = ACCU_TYPE_BITS_LEN / ACCU_ITEM_TYPE_BITS_LEN;
-        #[inline]
-        fn new_with_same(value: AccuType) -> Self {
-            Self([value; Self::LEN])
-        }
-        #[inline]
-        fn new_with(inner: StoreInner) -> Self {
-            Self(inner)
-        }
-        #[inline]
-        fn new_head_from_tail(tail: &Self) -> Self {
-            let mut inner = [0; Self::LEN];
-            for (i, slot) in inner.iter_mut().enumerate().take(Self::LEN) {
-                *slot = !*unsafe { tail.0.get_unchecked(i) };
-            }
-            Store::new_with(inner)
-        }
-        #[inline]
-        fn get(&self, i: usize) -> AccuItemType {
-            let slot_idx = Self::i_to_slot_idx(i);
-            (*unsafe { self.0.get_unchecked(slot_idx) }
-                >> ((i - slot_idx * Self::ITEMS_PER_SLOT) * ACCU_ITEM_TYPE_BITS_LEN))
-                as AccuItemType
-        }
-        #[inline]
-        fn i_to_slot_idx(i: usize) -> usize {
-            i / Self::ITEMS_PER_SLOT
-        }
-    }
-    // ===================
-    struct Mask {
-        tail: Store,
-        head: Store,
+    less_cheat_optimized_array(l).to_vec()
+}
+
+pub fn less_cheat_optimized_into(l: &[u32], out: &mut [u32; CAPACITY]) -> usize {
+    less_cheat_optimized_array(l).write_into(out)
+}
+
+pub fn try_less_cheat_optimized(l: &[u32]) -> Result<[u32; CAPACITY], Least8Error> {
+    less_cheat_optimized_array(l).into_full()
+}
+
+pub fn less_cheat_optimized_array(l: &[u32]) -> Least8 {
+    less_cheat_optimized_keys(
+        l.iter()
+            .filter(|i| **i <= u16::MAX as u32)
+            .map(|i| *i as u16),
+    )
+}
+
+// the packed store fed by any order-preserving u16 keys, see `signed`
+pub(crate) fn less_cheat_optimized_keys(keys: impl Iterator<Item = u16>) -> Least8 {
+    widen(packed_array::<u64, 2, u16>(keys))
+}
+
+fn widen(least8: Least8<u16>) -> Least8 {
+    Least8 {
+        values: least8.values.map(u32::from),
+        len: least8.len,
     }
-    impl Mask {
-        fn new(tail: Store) -> Self {
-            Self {
-                head: Store::new_head_from_tail(&tail),
-                tail,
+}
+
+#[cfg(feature = "alloc")]
+pub fn non_cheat_optimized(l: &[u32]) -> Vec<u32> {
+    non_cheat_optimized_array(l).to_vec()
+}
+
+pub fn non_cheat_optimized_into(l: &[u32], out: &mut [u32; CAPACITY]) -> usize {
+    non_cheat_optimized_array(l).write_into(out)
+}
+
+pub fn try_non_cheat_optimized(l: &[u32]) -> Result<[u32; CAPACITY], Least8Error> {
+    non_cheat_optimized_array(l).into_full()
+}
+
+pub fn non_cheat_optimized_array(l: &[u32]) -> Least8 {
+    non_cheat_optimized_keys(l.iter().copied())
+}
+
+// the packed store fed by any order-preserving u32 keys, see `float`
+pub(crate) fn non_cheat_optimized_keys(keys: impl Iterator<Item = u32>) -> Least8 {
+    packed_array::<u64, 4, u32>(keys)
+}
+
+#[cfg(feature = "alloc")]
+pub fn non_cheat_optimized_u64(l: &[u64]) -> Vec<u64> {
+    non_cheat_optimized_u64_array(l).to_vec()
+}
+
+// `non_cheat_optimized` for u64 values, one per u64 slot
+pub fn non_cheat_optimized_u64_array(l: &[u64]) -> Least8<u64> {
+    non_cheat_optimized_u64_keys(l.iter().copied())
+}
+
+// the same, two values per u128 slot
+pub fn non_cheat_optimized_u64_wide_array(l: &[u64]) -> Least8<u64> {
+    packed_array::<u128, 4, u64>(l.iter().copied())
+}
+
+// the packed store fed by any order-preserving u64 keys, see `float` and `signed`
+pub(crate) fn non_cheat_optimized_u64_keys(keys: impl Iterator<Item = u64>) -> Least8<u64> {
+    packed_array::<u64, 8, u64>(keys)
+}
+
+// an unsigned integer holding one or more items of a packed store
+pub trait Slot:
+    Copy
+    + Not<Output = Self>
+    + BitAnd<Output = Self>
+    + BitOr<Output = Self>
+    + BitAndAssign
+    + BitOrAssign
+{
+    const BITS: usize;
+    const ZERO: Self;
+    const MAX: Self;
+    // 0 once `n` shifts everything out, where `<<` and `>>` would overflow
+    fn shl(self, n: usize) -> Self;
+    fn shr(self, n: usize) -> Self;
+    // truncating
+    fn from_u128(value: u128) -> Self;
+    fn to_u128(self) -> u128;
+}
+
+// an unsigned integer packed into slots
+pub trait Item: Copy + Ord + Default {
+    const BITS: usize;
+    const MAX: Self;
+    // truncating
+    fn from_u128(value: u128) -> Self;
+    fn to_u128(self) -> u128;
+}
+
+macro_rules! impl_slot {
+    ($($t:ty),*) => {$(
+        impl Slot for $t {
+            const BITS: usize = <$t>::BITS as usize;
+            const ZERO: Self = 0;
+            const MAX: Self = <$t>::MAX;
+            #[inline(always)]
+            fn shl(self, n: usize) -> Self {
+                self.checked_shl(n as u32).unwrap_or(0)
             }
-        }
-    }
-    let mut masks = Vec::with_capacity(CAPACITY);
-    let mut tail = Store::new_with_same(AccuType::MAX);
-    masks.push(Mask::new(tail));
-    for i in 1..CAPACITY {
-        let slot_idx = Store::i_to_slot_idx(i);
-        if i % Store::ITEMS_PER_SLOT != 0 {
-            *unsafe { tail.0.get_unchecked_mut(slot_idx) } <<= ACCU_ITEM_TYPE_BITS_LEN;
-        }
-        masks.push(Mask::new(tail));
-    }
-    // ===================
-    let mut accu = Store::new_with_same(0);
-    let mut len = 0usize;
-    // ===================
-    use std::cmp::Ordering::*;
-    let mut right_value: AccuItemType = 0;
-    for element in l
-        .iter()
-        .filter(|i| **i <= AccuItemType::MAX as u32)
-        .map(|i| *i as AccuItemType)
-    {
-        if len == CAPACITY && element >= right_value {
-            continue;
-        }
-        let mut size = len;
-        let mut left = 0;
-        let mut right = size;
-        let index = loop {
-            if left >= right {
-                break left;
+            #[inline(always)]
+            fn shr(self, n: usize) -> Self {
+                self.checked_shr(n as u32).unwrap_or(0)
             }
-            let mid = left + size / 2;
-            let mid_value = accu.get(mid);
-            let cmp = element.cmp(&mid_value);
-            match cmp {
-                Greater => left = mid + 1,
-                Less => right = mid,
-                _ => break mid,
+            #[inline(always)]
+            fn from_u128(value: u128) -> Self {
+                value as $t
             }
-            size = right - left;
-        };
-        let need_set = if len < CAPACITY {
-            let slot_idx = Store::i_to_slot_idx(index);
-            let need_space = index < len;
-            len += 1;
-            Some((slot_idx, need_space))
-        } else if index < CAPACITY {
-            let slot_idx = Store::i_to_slot_idx(index);
-            let need_space = index < CAPACITY - 1;
-            Some((slot_idx, need_space))
-        } else {
-            None
-        };
-        if let Some((slot_idx, need_space)) = need_set {
-            if need_space {
-                let mask = unsafe { masks.get_unchecked(index) };
-                for i in ((slot_idx + 1)..Store::LEN).rev() {
-                    *unsafe { accu.0.get_unchecked_mut(i) } = (unsafe { accu.0.get_unchecked(i) }
-                        << ACCU_ITEM_TYPE_BITS_LEN)
-                        | (unsafe { accu.0.get_unchecked(i - 1) }
-                            >> (ACCU_TYPE_BITS_LEN - ACCU_ITEM_TYPE_BITS_LEN));
-                }
-                *unsafe { accu.0.get_unchecked_mut(slot_idx) } =
-                    ((unsafe { accu.0.get_unchecked(slot_idx) }
-                        & unsafe { mask.tail.0.get_unchecked(slot_idx) })
-                        << ACCU_ITEM_TYPE_BITS_LEN)
-                        | (unsafe { accu.0.get_unchecked(slot_idx) }
-                            & unsafe { mask.head.0.get_unchecked(slot_idx) });
+            #[inline(always)]
+            fn to_u128(self) -> u128 {
+                self as u128
             }
-            *unsafe { accu.0.get_unchecked_mut(slot_idx) } |= (element as AccuType)
-                << ((index - slot_idx * Store::ITEMS_PER_SLOT) * ACCU_ITEM_TYPE_BITS_LEN);
-            right_value = if index == len - 1 {
-                element
-            } else {
-                accu.get(len - 1)
-            };
         }
-    }
+    )*};
+}
+impl_slot!(u64, u128);
 
-    #[inline]
-    fn accu_to_vec(accu: Store, len: usize) -> Vec<u32> {
-        let mut ret = Vec::with_capacity(CAPACITY);
-        let mut mask = AccuItemType::MAX as AccuType;
-        for i in 0..len {
-            let slot_idx = Store::i_to_slot_idx(i);
-            ret.push(
-                ((unsafe { accu.0.get_unchecked(slot_idx) } & mask)
-                    >> ((i - slot_idx * Store::ITEMS_PER_SLOT) * ACCU_ITEM_TYPE_BITS_LEN))
-                    as u32,
-            );
-            if i % Store::ITEMS_PER_SLOT == Store::ITEMS_PER_SLOT - 1 {
-                mask = AccuItemType::MAX as AccuType
-            } else {
-                mask <<= ACCU_ITEM_TYPE_BITS_LEN;
+macro_rules! impl_item {
+    ($($t:ty),*) => {$(
+        impl Item for $t {
+            const BITS: usize = <$t>::BITS as usize;
+            const MAX: Self = <$t>::MAX;
+            #[inline(always)]
+            fn from_u128(value: u128) -> Self {
+                value as $t
+            }
+            #[inline(always)]
+            fn to_u128(self) -> u128 {
+                self as u128
             }
         }
-        ret
-    }
-
-    accu_to_vec(accu, len)
+    )*};
 }
+impl_item!(u16, u32, u64);
 
-pub fn non_cheat_optimized(l: &[u32]) -> Vec<u32> {
-    type AccuType = u64;
-    const ACCU_TYPE_BITS_LEN: usize = 64;
-    type AccuItemType = u32;
-    const ACCU_ITEM_TYPE_BITS_LEN: usize = 32;
-    // ===================
-    type StoreInner = [AccuType; Store::LEN];
-    #[derive(Clone, Copy)]
-    struct Store(StoreInner);
-    impl Store {
-        const LEN: usize = (ACCU_ITEM_TYPE_BITS_LEN * CAPACITY) / ACCU_TYPE_BITS_LEN;
-        const ITEMS_PER_SLOT: usize = ACCU_TYPE_BITS_LEN / ACCU_ITEM_TYPE_BITS_LEN;
-        #[inline]
-        fn new_with_same(value: AccuType) -> Self {
-            Self([value; 4])
-        }
-        #[inline]
-        fn new_with(inner: StoreInner) -> Self {
-            Self(inner)
-        }
-        #[inline]
-        fn new_head_from_tail(tail: &Self) -> Self {
-            let mut inner = [0; Self::LEN];
-            for (i, slot) in inner.iter_mut().enumerate().take(Self::LEN) {
-                *slot = !*unsafe { tail.0.get_unchecked(i) };
-            }
-            Store::new_with(inner)
-        }
-        #[inline]
-        fn get(&self, i: usize) -> AccuItemType {
-            let slot_idx = Self::i_to_slot_idx(i);
-            (*unsafe { self.0.get_unchecked(slot_idx) }
-                >> ((i - slot_idx * Self::ITEMS_PER_SLOT) * ACCU_ITEM_TYPE_BITS_LEN))
-                as AccuItemType
-        }
-        #[inline]
-        fn i_to_slot_idx(i: usize) -> usize {
-            i / Self::ITEMS_PER_SLOT
+// CAPACITY items of type `I` packed into `LEN` slots of type `S`, sorted ascending from the
+// lowest bits of the first slot
+#[derive(Clone, Copy)]
+struct Store<S, const LEN: usize>([S; LEN]);
+
+impl<S: Slot, const LEN: usize> Store<S, LEN> {
+    const ITEMS_PER_SLOT: usize = CAPACITY / LEN;
+    const ITEM_BITS: usize = S::BITS / Self::ITEMS_PER_SLOT;
+    #[inline]
+    fn new_with_same(value: S) -> Self {
+        Self([value; LEN])
+    }
+    #[inline]
+    fn new_head_from_tail(tail: &Self) -> Self {
+        let mut inner = [S::ZERO; LEN];
+        for (i, slot) in inner.iter_mut().enumerate() {
+            *slot = !*at(&tail.0, i);
         }
+        Self(inner)
     }
-    // ===================
-    struct Mask {
-        tail: Store,
-        head: Store,
+    #[inline]
+    fn get<I: Item>(&self, i: usize) -> I {
+        let slot_idx = Self::i_to_slot_idx(i);
+        I::from_u128(
+            at(&self.0, slot_idx)
+                .shr((i - slot_idx * Self::ITEMS_PER_SLOT) * Self::ITEM_BITS)
+                .to_u128(),
+        )
     }
-    impl Mask {
-        fn new(tail: Store) -> Self {
-            Self {
-                head: Store::new_head_from_tail(&tail),
-                tail,
-            }
+    #[inline]
+    fn i_to_slot_idx(i: usize) -> usize {
+        i / Self::ITEMS_PER_SLOT
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Mask<S, const LEN: usize> {
+    tail: Store<S, LEN>,
+    head: Store<S, LEN>,
+}
+
+impl<S: Slot, const LEN: usize> Mask<S, LEN> {
+    fn new(tail: Store<S, LEN>) -> Self {
+        Self {
+            head: Store::new_head_from_tail(&tail),
+            tail,
         }
     }
-    let mut masks = Vec::with_capacity(CAPACITY);
-    let mut tail = Store::new_with_same(AccuType::MAX);
-    masks.push(Mask::new(tail));
-    for i in 1..CAPACITY {
-        let slot_idx = Store::i_to_slot_idx(i);
-        if i % Store::ITEMS_PER_SLOT != 0 {
-            *unsafe { tail.0.get_unchecked_mut(slot_idx) } <<= ACCU_ITEM_TYPE_BITS_LEN;
+}
+
+// the store of `non_cheat_optimized` with the slot type and the item width as parameters:
+// `packed_array::<u64, 4, u32>` packs u32 items into `[u64; 4]`, `packed_array::<u128, 4, u64>`
+// u64 items into `[u128; 4]`; the items must fill the slots exactly
+pub fn packed_array<S: Slot, const LEN: usize, I: Item>(
+    keys: impl IntoIterator<Item = I>,
+) -> Least8<I> {
+    packed_least8::<S, LEN, I, true>(keys)
+}
+
+fn packed_least8<S: Slot, const LEN: usize, I: Item, const GUARD: bool>(
+    keys: impl IntoIterator<Item = I>,
+) -> Least8<I> {
+    const {
+        assert!(S::BITS * LEN == I::BITS * CAPACITY && CAPACITY.is_multiple_of(LEN));
+    }
+    let item_bits = Store::<S, LEN>::ITEM_BITS;
+    let items_per_slot = Store::<S, LEN>::ITEMS_PER_SLOT;
+    // ===================
+    let mut tail = Store::<S, LEN>::new_with_same(S::MAX);
+    let mut masks = [Mask::new(tail); CAPACITY];
+    for (i, mask) in masks.iter_mut().enumerate().skip(1) {
+        let slot_idx = Store::<S, LEN>::i_to_slot_idx(i);
+        if i % items_per_slot != 0 {
+            let slot = at_mut(&mut tail.0, slot_idx);
+            *slot = slot.shl(item_bits);
         }
-        masks.push(Mask::new(tail));
+        *mask = Mask::new(tail);
     }
     // ===================
-    let mut accu = Store::new_with_same(0);
+    let mut accu = Store::<S, LEN>::new_with_same(S::ZERO);
     let mut len = 0usize;
     // ===================
-    use std::cmp::Ordering::*;
-    let mut right_value: AccuItemType = 0;
-    for element in l.iter().copied() {
-        if len == CAPACITY && element >= right_value {
+    use core::cmp::Ordering::*;
+    let mut right_value = I::default();
+    for element in keys {
+        if GUARD && len == CAPACITY && element >= right_value {
             continue;
         }
         let mut size = len;
@@ -352,7 +638,7 @@ pub fn non_cheat_optimized(l: &[u32]) -> Vec<u32> {
                 break left;
             }
             let mid = left + size / 2;
-            let mid_value = accu.get(mid);
+            let mid_value: I = accu.get(mid);
             let cmp = element.cmp(&mid_value);
             match cmp {
                 Greater => left = mid + 1,
@@ -362,12 +648,12 @@ pub fn non_cheat_optimized(l: &[u32]) -> Vec<u32> {
             size = right - left;
         };
         let need_set = if len < CAPACITY {
-            let slot_idx = Store::i_to_slot_idx(index);
+            let slot_idx = Store::<S, LEN>::i_to_slot_idx(index);
             let need_space = index < len;
             len += 1;
             Some((slot_idx, need_space))
         } else if index < CAPACITY {
-            let slot_idx = Store::i_to_slot_idx(index);
+            let slot_idx = Store::<S, LEN>::i_to_slot_idx(index);
             let need_space = index < CAPACITY - 1;
             Some((slot_idx, need_space))
         } else {
@@ -375,22 +661,20 @@ pub fn non_cheat_optimized(l: &[u32]) -> Vec<u32> {
         };
         if let Some((slot_idx, need_space)) = need_set {
             if need_space {
-                let mask = unsafe { masks.get_unchecked(index) };
-                for i in ((slot_idx + 1)..Store::LEN).rev() {
-                    *unsafe { accu.0.get_unchecked_mut(i) } = (unsafe { accu.0.get_unchecked(i) }
-                        << ACCU_ITEM_TYPE_BITS_LEN)
-                        | (unsafe { accu.0.get_unchecked(i - 1) }
-                            >> (ACCU_TYPE_BITS_LEN - ACCU_ITEM_TYPE_BITS_LEN));
+                let mask = at(&masks, index);
+                for i in ((slot_idx + 1)..LEN).rev() {
+                    *at_mut(&mut accu.0, i) =
+                        at(&accu.0, i).shl(item_bits) | at(&accu.0, i - 1).shr(S::BITS - item_bits);
                 }
-                *unsafe { accu.0.get_unchecked_mut(slot_idx) } =
-                    ((unsafe { accu.0.get_unchecked(slot_idx) }
-                        & unsafe { mask.tail.0.get_unchecked(slot_idx) })
-                        << ACCU_ITEM_TYPE_BITS_LEN)
-                        | (unsafe { accu.0.get_unchecked(slot_idx) }
-                            & unsafe { mask.head.0.get_unchecked(slot_idx) });
+                *at_mut(&mut accu.0, slot_idx) =
+                    (*at(&accu.0, slot_idx) & *at(&mask.tail.0, slot_idx)).shl(item_bits)
+                        | (*at(&accu.0, slot_idx) & *at(&mask.head.0, slot_idx));
             }
-            *unsafe { accu.0.get_unchecked_mut(slot_idx) } |= (element as AccuType)
-                << ((index - slot_idx * Store::ITEMS_PER_SLOT) * ACCU_ITEM_TYPE_BITS_LEN);
+            let shift = (index - slot_idx * items_per_slot) * item_bits;
+            // the rightmost item is overwritten in place when the accu is full, so clear it first
+            let slot = at_mut(&mut accu.0, slot_idx);
+            *slot &= !S::from_u128(I::MAX.to_u128()).shl(shift);
+            *slot |= S::from_u128(element.to_u128()).shl(shift);
             right_value = if index == len - 1 {
                 element
             } else {
@@ -399,108 +683,215 @@ pub fn non_cheat_optimized(l: &[u32]) -> Vec<u32> {
         }
     }
 
-    #[inline]
-    fn accu_to_vec(accu: Store, len: usize) -> Vec<u32> {
-        let mut ret = Vec::with_capacity(CAPACITY);
-        let mut mask = AccuItemType::MAX as AccuType;
-        for i in 0..len {
-            let slot_idx = Store::i_to_slot_idx(i);
-            ret.push(
-                ((unsafe { accu.0.get_unchecked(slot_idx) } & mask)
-                    >> ((i - slot_idx * Store::ITEMS_PER_SLOT) * ACCU_ITEM_TYPE_BITS_LEN))
-                    as u32,
-            );
-            if i % Store::ITEMS_PER_SLOT == Store::ITEMS_PER_SLOT - 1 {
-                mask = AccuItemType::MAX as AccuType
-            } else {
-                mask <<= ACCU_ITEM_TYPE_BITS_LEN;
+    let mut ret = Least8 {
+        len,
+        ..Least8::default()
+    };
+    for (i, value) in ret.values.iter_mut().enumerate().take(len) {
+        *value = accu.get(i);
+    }
+    ret
+}
+
+#[cfg(feature = "std")]
+pub fn thread_optimized(l: &[u32]) -> Vec<u32> {
+    thread_optimized_array(l).to_vec()
+}
+
+#[cfg(feature = "std")]
+pub fn thread_optimized_into(l: &[u32], out: &mut [u32; CAPACITY]) -> usize {
+    thread_optimized_array(l).write_into(out)
+}
+
+#[cfg(feature = "std")]
+pub fn try_thread_optimized(l: &[u32]) -> Result<[u32; CAPACITY], Least8Error> {
+    thread_optimized_least8::<true>(l)?.into_full()
+}
+
+#[cfg(feature = "std")]
+pub fn thread_optimized_array(l: &[u32]) -> Least8 {
+    thread_optimized_least8::<true>(l).unwrap_or_else(|err| panic!("{err}"))
+}
+
+#[cfg(feature = "std")]
+pub(crate) const MAX_THREADS_COUNT: usize = 4;
+
+// every chunk must hold at least CAPACITY elements, or the unfilled tail of its result is merged too
+#[cfg(feature = "std")]
+pub(crate) fn threads_count(len: usize) -> usize {
+    (len / CAPACITY).clamp(1, MAX_THREADS_COUNT)
+}
+
+// `l` split into `threads_count` consecutive chunks of the same length, the last one also taking
+// the remainder
+#[cfg(feature = "std")]
+pub(crate) fn chunks<T>(l: &[T], threads_count: usize) -> impl Iterator<Item = &[T]> {
+    let len = l.len() / threads_count;
+    (0..threads_count).map(move |i| {
+        if i < threads_count - 1 {
+            &l[len * i..len * (i + 1)]
+        } else {
+            &l[len * i..]
+        }
+    })
+}
+
+// `worker` on every item on its own scoped thread, the results in item order; every thread that
+// started is joined before returning, also when a later one fails to
+#[cfg(feature = "std")]
+pub(crate) fn scoped_workers<I: Send, R: Send>(
+    items: impl IntoIterator<Item = I>,
+    worker: impl Fn(I) -> R + Sync,
+) -> Result<Vec<R>, Least8Error> {
+    use std::thread;
+    let worker = &worker;
+    let (results, spawn_failed) = thread::scope(|scope| {
+        let mut threads = vec![];
+        let mut spawn_failed = false;
+        for item in items {
+            match thread::Builder::new().spawn_scoped(scope, move || worker(item)) {
+                Ok(thread) => threads.push(thread),
+                Err(_) => {
+                    spawn_failed = true;
+                    break;
+                }
             }
         }
-        ret
+        let results: Vec<_> = threads.into_iter().map(|thread| thread.join()).collect();
+        (results, spawn_failed)
+    });
+    let results = results
+        .into_iter()
+        .map(|result| result.map_err(|_| Least8Error::WorkerPanicked))
+        .collect::<Result<Vec<_>, _>>()?;
+    if spawn_failed {
+        return Err(Least8Error::SpawnFailed);
     }
-
-    accu_to_vec(accu, len)
+    Ok(results)
 }
 
-pub fn thread_optimized(l: &[u32]) -> Vec<u32> {
+// apart from what spawning the threads takes inside std, nothing here touches the heap
+#[cfg(all(feature = "std", not(feature = "safe")))]
+fn thread_optimized_least8<const GUARD: bool>(l: &[u32]) -> Result<Least8, Least8Error> {
     use std::thread;
-    const MAX_THREADS_COUNT: usize = 4;
-    let threads_count = std::cmp::min(
-        MAX_THREADS_COUNT,
-        l.len() / CAPACITY + if l.len() % CAPACITY == 0 { 0 } else { 1 },
-    );
-    let mut rets = Vec::with_capacity(threads_count);
-    let l_len = l.len();
-    let len = l_len / threads_count;
-    let mut threads = Vec::with_capacity(threads_count);
-    for i in 0..threads_count {
-        let mut ret = Box::new([0u32; CAPACITY]);
-        let ret_ptr: *mut u32 = (*ret).as_mut_ptr();
+    let threads_count = threads_count(l.len());
+    let mut rets = [[0u32; CAPACITY]; MAX_THREADS_COUNT];
+    let ret_ptrs = rets.each_mut().map(|ret| ret.as_mut_ptr());
+    let mut threads: [_; MAX_THREADS_COUNT] = core::array::from_fn(|_| None);
+    for (i, chunk) in chunks(l, threads_count).enumerate() {
         let b = MyBox {
-            len: if i < threads_count - 1 {
-                len
-            } else {
-                l_len - len * i
-            },
-            ptr: unsafe { l.as_ptr().add(len * i) },
-            ret_ptr,
+            len: chunk.len(),
+            ptr: chunk.as_ptr(),
+            ret_ptr: ret_ptrs[i],
         };
-        rets.push(ret);
-        threads.push(thread::spawn(move || unsafe {
-            thread_optimized_helper(b);
-        }));
+        let spawned =
+            thread::Builder::new().spawn(move || unsafe { thread_optimized_helper::<GUARD>(b) });
+        match spawned {
+            Ok(thread) => threads[i] = Some(thread),
+            Err(_) => break,
+        }
     }
-    for thread in threads {
-        thread.join().unwrap();
+    // every started thread is joined before returning, even if a later one failed to start,
+    // they write to `rets`
+    let mut lens = [0; MAX_THREADS_COUNT];
+    let mut spawned = 0;
+    let mut panicked = false;
+    for (len, thread) in lens.iter_mut().zip(threads) {
+        if let Some(thread) = thread {
+            spawned += 1;
+            match thread.join() {
+                Ok(thread_len) => *len = thread_len,
+                Err(_) => panicked = true,
+            }
+        }
     }
-    let mut idxs = vec![0; threads_count];
-    let mut values = Vec::with_capacity(threads_count);
-    for i in 0..threads_count {
-        values.push(rets[i].get(idxs[i]));
+    if panicked {
+        return Err(Least8Error::WorkerPanicked);
     }
-    let mut ret = Vec::with_capacity(CAPACITY);
-    loop {
-        let mut min_value_wrapper: Option<(usize, u32)> = None;
-        for (i, value) in values
-            .iter()
-            .enumerate()
-            .filter_map(|(i, value)| value.map(|value| (i, value)))
-        {
-            let need_update = if let Some((_, min_value)) = min_value_wrapper {
-                *value < min_value
-            } else {
-                true
-            };
-            if need_update {
-                min_value_wrapper = Some((i, *value));
+    if spawned < threads_count {
+        return Err(Least8Error::SpawnFailed);
+    }
+    let parts: [&[u32]; MAX_THREADS_COUNT] = core::array::from_fn(|i| &rets[i][..lens[i]]);
+    Ok(merge_array(&parts[..threads_count]))
+}
+
+// the same chunks, borrowed by scoped threads running `optimized_array`
+#[cfg(all(feature = "std", feature = "safe"))]
+fn thread_optimized_least8<const GUARD: bool>(l: &[u32]) -> Result<Least8, Least8Error> {
+    use std::thread;
+    let threads_count = threads_count(l.len());
+    let mut rets = [Least8::default(); MAX_THREADS_COUNT];
+    thread::scope(|scope| {
+        let mut threads: [_; MAX_THREADS_COUNT] = core::array::from_fn(|_| None);
+        for (thread, chunk) in threads.iter_mut().zip(chunks(l, threads_count)) {
+            let spawned = thread::Builder::new()
+                .spawn_scoped(scope, move || optimized_least8::<GUARD>(chunk));
+            match spawned {
+                Ok(spawned) => *thread = Some(spawned),
+                Err(_) => break,
+            }
+        }
+        let mut spawned = 0;
+        let mut panicked = false;
+        for (ret, thread) in rets.iter_mut().zip(threads) {
+            if let Some(thread) = thread {
+                spawned += 1;
+                match thread.join() {
+                    Ok(thread_ret) => *ret = thread_ret,
+                    Err(_) => panicked = true,
+                }
             }
         }
-        if let Some((i_of_min_value, min_value)) = min_value_wrapper {
-            ret.push(min_value);
-            idxs[i_of_min_value] += 1;
-            values[i_of_min_value] = rets[i_of_min_value].get(idxs[i_of_min_value]);
+        if panicked {
+            return Err(Least8Error::WorkerPanicked);
         }
-        if ret.len() == CAPACITY {
-            break;
+        if spawned < threads_count {
+            return Err(Least8Error::SpawnFailed);
+        }
+        Ok(())
+    })?;
+    let parts: [&[u32]; MAX_THREADS_COUNT] = core::array::from_fn(|i| rets[i].as_slice());
+    Ok(merge_array(&parts[..threads_count]))
+}
+
+// merges sorted partial results (of any length) into the CAPACITY smallest values
+#[cfg(feature = "alloc")]
+pub fn merge(rets: &[&[u32]]) -> Vec<u32> {
+    merge_array(rets).to_vec()
+}
+
+pub fn merge_array(rets: &[&[u32]]) -> Least8 {
+    let mut ret = Least8::default();
+    for part in rets {
+        for value in *part {
+            // the rest of a sorted part can't get in either
+            if !ret.insert(*value) {
+                break;
+            }
         }
     }
     ret
 }
 
+#[cfg(all(feature = "std", not(feature = "safe")))]
 struct MyBox {
     ptr: *const u32,
     len: usize,
     ret_ptr: *mut u32,
 }
+#[cfg(all(feature = "std", not(feature = "safe")))]
 unsafe impl Send for MyBox {}
+#[cfg(all(feature = "std", not(feature = "safe")))]
 unsafe impl Sync for MyBox {}
 
-unsafe fn thread_optimized_helper(arg: MyBox) {
+#[cfg(all(feature = "std", not(feature = "safe")))]
+// returns how many values it wrote to `arg.ret_ptr`
+unsafe fn thread_optimized_helper<const GUARD: bool>(arg: MyBox) -> usize {
     let mut len = 0usize;
     let mut right_value: u32 = 0;
     for i in 0..arg.len {
         let element = *arg.ptr.add(i);
-        if len == CAPACITY && element >= right_value {
+        if GUARD && len == CAPACITY && element >= right_value {
             continue;
         }
         let mut size = len;
@@ -512,7 +903,7 @@ unsafe fn thread_optimized_helper(arg: MyBox) {
             }
             let mid = left + size / 2;
             let cmp = element.cmp(&*arg.ret_ptr.add(mid));
-            use std::cmp::Ordering::*;
+            use core::cmp::Ordering::*;
             match cmp {
                 Greater => left = mid + 1,
                 Less => right = mid,
@@ -544,4 +935,5 @@ unsafe fn thread_optimized_helper(arg: MyBox) {
             };
         }
     }
+    len
 }
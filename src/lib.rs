@@ -1,18 +1,61 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+#![cfg_attr(feature = "safe", forbid(unsafe_code))]
+
+#[cfg(feature = "alloc")]
+extern crate alloc;
+
+#[cfg(feature = "alloc")]
+use alloc::vec::Vec;
+
+#[cfg(feature = "alloc")]
+pub mod accumulator;
+#[cfg(feature = "std")]
+pub mod bench;
+#[cfg(feature = "std")]
+pub mod cli;
+#[cfg(feature = "alloc")]
+pub mod dataset;
+#[cfg(feature = "alloc")]
+pub mod dynamic;
+pub mod error;
+#[cfg(feature = "std")]
+pub mod file;
+#[cfg(feature = "alloc")]
+pub mod float;
+#[cfg(feature = "std")]
+pub mod format;
+#[cfg(feature = "std")]
+pub mod grouped;
+#[cfg(feature = "std")]
+pub mod http;
 pub mod implementation;
+#[cfg(feature = "std")]
+pub mod json;
+#[cfg(feature = "alloc")]
+mod multiset;
+#[cfg(feature = "std")]
+pub mod output;
+#[cfg(feature = "alloc")]
+pub mod pairs;
+#[cfg(feature = "alloc")]
+pub mod query;
+#[cfg(feature = "std")]
+pub mod repl;
+#[cfg(feature = "std")]
+pub mod server;
+#[cfg(feature = "alloc")]
+pub mod signed;
+#[cfg(feature = "alloc")]
+pub mod sliding;
+#[cfg(feature = "alloc")]
+pub mod timed;
 
+#[cfg(feature = "alloc")]
 pub fn make_list() -> Vec<u32> {
-    const SIZE: usize = 1 << 16;
-    let mut out = Vec::with_capacity(SIZE);
-    let mut num = 998_244_353_u32; // prime
-    for i in 0..SIZE {
-        out.push(num);
-        // rotate and add to produce some pseudorandomness
-        num = (((num << 1) | (num >> 31)) as u64 + (i as u64)) as u32;
-    }
-    out
+    dataset::Distribution::MakeList.generate(1 << 16, dataset::DEFAULT_SEED)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "alloc"))]
 mod tests {
     use super::*;
 
@@ -47,6 +90,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn thread_optimized() {
         let l = make_list();
         let l8 = implementation::thread_optimized(&l);
@@ -54,6 +98,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn thread_optimized_edge_case() {
         let mut l = make_list();
         l.push(38);
@@ -62,6 +107,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn thread_optimized_edge_case2() {
         let mut l = make_list();
         l.push(37);
@@ -69,6 +115,14 @@ mod tests {
         assert_eq!(vec![4, 5, 15, 22, 28, 31, 37, 37], l8);
     }
 
+    #[test]
+    #[cfg(feature = "std")]
+    fn thread_optimized_short_chunks() {
+        let l: Vec<u32> = (1..=20).collect();
+        let l8 = implementation::thread_optimized(&l);
+        assert_eq!(vec![1, 2, 3, 4, 5, 6, 7, 8], l8);
+    }
+
     #[test]
     fn cheat_optimized() {
         let l = make_list();
@@ -137,4 +191,151 @@ mod tests {
         let l8 = implementation::non_cheat_optimized(&l);
         assert_eq!(vec![4, 5, 15, 22, 28, 31, 37, 37], l8);
     }
+    // replacing the rightmost of 8 selected values must not merge it with the evicted one
+    const REPLACE_LAST: [u32; 9] = [10, 20, 30, 40, 50, 60, 70, 80, 75];
+
+    #[test]
+    fn cheat_optimized_replace_last() {
+        let l8 = implementation::cheat_optimized(&REPLACE_LAST);
+        assert_eq!(vec![10, 20, 30, 40, 50, 60, 70, 75], l8);
+    }
+
+    #[test]
+    fn less_cheat_optimized_replace_last() {
+        let l8 = implementation::less_cheat_optimized(&REPLACE_LAST);
+        assert_eq!(vec![10, 20, 30, 40, 50, 60, 70, 75], l8);
+    }
+
+    #[test]
+    fn non_cheat_optimized_replace_last() {
+        let l8 = implementation::non_cheat_optimized(&REPLACE_LAST);
+        assert_eq!(vec![10, 20, 30, 40, 50, 60, 70, 75], l8);
+    }
+
+    #[test]
+    fn non_cheat_optimized_u64() {
+        // the high halves differ where the low halves would sort the other way
+        let mut l: Vec<u64> = make_list()
+            .into_iter()
+            .map(|value| u64::from(value).rotate_right(7) ^ u64::from(value))
+            .collect();
+        l.extend([u64::MAX, 0, u64::MAX - 1, 1 << 32, (1 << 32) - 1, 1 << 63]);
+        l.extend(REPLACE_LAST.map(u64::from));
+        let mut expected = l.clone();
+        expected.sort();
+        expected.truncate(implementation::CAPACITY);
+        assert_eq!(expected, implementation::non_cheat_optimized_u64(&l));
+        assert_eq!(
+            expected,
+            *implementation::non_cheat_optimized_u64_wide_array(&l)
+        );
+        let short = [u64::MAX, 3, u64::MAX, 1 << 40];
+        assert_eq!(
+            vec![3, 1 << 40, u64::MAX, u64::MAX],
+            implementation::non_cheat_optimized_u64(&short)
+        );
+        assert_eq!(
+            [3, 1 << 40, u64::MAX, u64::MAX],
+            *implementation::non_cheat_optimized_u64_wide_array(&short)
+        );
+    }
+
+    #[test]
+    fn try_variants() {
+        use error::Least8Error;
+        type Try = fn(&[u32]) -> Result<[u32; 8], Least8Error>;
+        let tries: &[(&str, Try)] = &[
+            ("naive", implementation::try_naive),
+            ("optimized", implementation::try_optimized),
+            #[cfg(feature = "std")]
+            ("thread_optimized", implementation::try_thread_optimized),
+            ("cheat_optimized", implementation::try_cheat_optimized),
+            (
+                "less_cheat_optimized",
+                implementation::try_less_cheat_optimized,
+            ),
+            (
+                "non_cheat_optimized",
+                implementation::try_non_cheat_optimized,
+            ),
+        ];
+        let l = make_list();
+        for &(name, try_least_8) in tries {
+            assert_eq!(
+                Ok([4, 5, 15, 22, 28, 31, 37, 38]),
+                try_least_8(&l),
+                "{name}"
+            );
+            assert_eq!(
+                Err(Least8Error::NotEnoughElements { found: 0 }),
+                try_least_8(&[]),
+                "{name}"
+            );
+            assert_eq!(
+                Err(Least8Error::NotEnoughElements { found: 3 }),
+                try_least_8(&[3, 1, 2]),
+                "{name}"
+            );
+        }
+        // the cheating strategies only count what they can pack
+        let l = [1, 2, 3, 1000, 1001, 1002, 1003, 1004, 100_000];
+        assert_eq!(
+            Err(Least8Error::NotEnoughElements { found: 3 }),
+            implementation::try_cheat_optimized(&l)
+        );
+        for try_least_8 in [
+            implementation::try_less_cheat_optimized,
+            implementation::try_least_8,
+        ] {
+            assert_eq!(Ok([1, 2, 3, 1000, 1001, 1002, 1003, 1004]), try_least_8(&l));
+        }
+        assert_eq!(
+            "not enough elements: need 8, found 3",
+            Least8Error::NotEnoughElements { found: 3 }.to_string()
+        );
+    }
+}
+
+// the allocation-free strategies, also run without `alloc`
+#[cfg(test)]
+mod array_tests {
+    use super::*;
+
+    #[test]
+    fn array_short_input() {
+        let l = [9, 3, 7, 1];
+        for least8 in [
+            implementation::naive_array(&l),
+            implementation::optimized_array(&l),
+            implementation::cheat_optimized_array(&l),
+            implementation::less_cheat_optimized_array(&l),
+            implementation::non_cheat_optimized_array(&l),
+        ] {
+            assert_eq!(&[1, 3, 7, 9], least8.as_slice());
+            assert_eq!([1, 3, 7, 9, 0, 0, 0, 0], least8.into_array());
+        }
+    }
+
+    #[test]
+    fn naive_array_duplicates() {
+        let l = [5, 1, 5, 9, 1, 5, 5, 5, 5, 5, 5, 0];
+        assert_eq!(
+            &[0, 1, 1, 5, 5, 5, 5, 5],
+            implementation::naive_array(&l).as_slice()
+        );
+    }
+
+    #[test]
+    fn array_replace_last() {
+        let l = [10, 20, 30, 40, 50, 60, 70, 80, 75];
+        for least8 in [
+            implementation::naive_array(&l),
+            implementation::optimized_array(&l),
+            implementation::cheat_optimized_array(&l),
+            implementation::less_cheat_optimized_array(&l),
+            implementation::non_cheat_optimized_array(&l),
+        ] {
+            assert_eq!([10, 20, 30, 40, 50, 60, 70, 75], least8.into_array());
+        }
+    }
 }
@@ -1,6 +1,70 @@
+use least_8::bench::{self, Config};
+use least_8::cli::{self, GenerateOptions, Options, ServeOptions, VerifyOptions};
+use least_8::dataset::Distribution;
+use least_8::output::Report;
+use least_8::repl::Repl;
 use least_8::{implementation, make_list};
 
 fn main() {
+    let args: Vec<_> = std::env::args().skip(1).collect();
+    let result = match args.first().map(String::as_str) {
+        Some("readme") => readme(args[1..].to_vec()),
+        Some("generate") => GenerateOptions::parse(args[1..].to_vec())
+            .and_then(|options| options.generate())
+            .map(|_| ()),
+        Some("repl") => repl(&args[1..]),
+        Some("serve") => {
+            ServeOptions::parse(args[1..].to_vec()).and_then(|options| options.serve())
+        }
+        Some("verify") => VerifyOptions::parse(args[1..].to_vec()).and_then(|options| {
+            if options.run(&mut std::io::stdout().lock())? {
+                Ok(())
+            } else {
+                std::process::exit(1)
+            }
+        }),
+        _ => select(args),
+    };
+    if let Err(err) = result {
+        eprintln!("{err}");
+        std::process::exit(2);
+    }
+}
+
+fn select(args: Vec<String>) -> Result<(), String> {
+    let options = Options::parse(args)?;
+    if options.help {
+        print!("{}", cli::USAGE);
+        return Ok(());
+    }
+    if options.time {
+        time();
+        return Ok(());
+    }
+    let start = std::time::Instant::now();
+    let selection = options.select()?;
+    let report = Report {
+        selection: &selection,
+        strategy: options.query.strategy_name(),
+        exact: options.query.is_exact(),
+        elapsed: start.elapsed(),
+    };
+    report
+        .write(options.output, &mut std::io::stdout().lock())
+        .map_err(|err| err.to_string())
+}
+
+fn repl(args: &[String]) -> Result<(), String> {
+    if let Some(arg) = args.first() {
+        return Err(format!("unknown repl argument {arg:?}"));
+    }
+    println!("least_8 repl, type help for the commands");
+    Repl::new()
+        .run(std::io::stdin().lock(), &mut std::io::stdout().lock(), true)
+        .map_err(|err| err.to_string())
+}
+
+fn time() {
     let l = make_list();
     //
     let start = std::time::Instant::now();
@@ -51,3 +115,42 @@ fn main() {
         end.duration_since(start)
     );
 }
+
+// usage: least_8 readme [--readme README.md] [bench options, see benches/bench.rs]
+// runs the suite on `make_list`, with and without the rightmost guard, and rewrites the generated
+// sections of the README
+fn readme(mut args: Vec<String>) -> Result<(), String> {
+    let mut path = "README.md".to_owned();
+    if let Some(i) = args.iter().position(|arg| arg == "--readme") {
+        path = args.get(i + 1).ok_or("--readme needs a value")?.clone();
+        args.drain(i..i + 2);
+    }
+    if let Some(arg) = args
+        .iter()
+        .find(|arg| *arg == "--sizes" || *arg == "--dist")
+    {
+        return Err(format!(
+            "{arg} can't be used with readme, which measures the inputs the README describes"
+        ));
+    }
+    let mut content =
+        std::fs::read_to_string(&path).map_err(|err| format!("failed to read {path}: {err}"))?;
+    // the generated sections of the README and the strategies they measure
+    for (name, strategies) in [
+        ("intermediate-table", implementation::UNGUARDED_STRATEGIES),
+        ("benchmark-table", implementation::STRATEGIES),
+    ] {
+        let config = Config {
+            strategies,
+            sizes: vec![make_list().len()],
+            distributions: vec![Distribution::MakeList],
+            ..Config::from_args(args.clone())?
+        };
+        let measurements = bench::run(&config, |measurement| {
+            eprintln!("{}", bench::format_row(measurement))
+        });
+        let table = bench::markdown_table(&measurements, Distribution::MakeList);
+        content = bench::replace_marked_section(&content, name, &table)?;
+    }
+    std::fs::write(&path, content).map_err(|err| format!("failed to write {path}: {err}"))
+}
@@ -0,0 +1,74 @@
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::implementation::CAPACITY;
+
+// the insertion buffer of `implementation::optimized` over a multiset that also forgets values:
+// the buffer keeps the CAPACITY smallest, everything else is counted in a balanced tree, so the
+// buffer can be refilled with the next smallest when one of its values is removed
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Least8Set {
+    // ascending, none larger than any value in `rest`; `rest` is empty until it is full
+    buffer: Vec<u32>,
+    // value -> count
+    rest: BTreeMap<u32, usize>,
+    len: usize,
+}
+
+impl Least8Set {
+    pub(crate) fn len(&self) -> usize {
+        self.len
+    }
+
+    pub(crate) fn least_8(&self) -> &[u32] {
+        &self.buffer
+    }
+
+    pub(crate) fn insert(&mut self, value: u32) {
+        self.len += 1;
+        if self.buffer.len() == CAPACITY {
+            // rightmost guard
+            let right = self.buffer[CAPACITY - 1];
+            if value >= right {
+                *self.rest.entry(value).or_default() += 1;
+                return;
+            }
+            self.buffer.pop();
+            *self.rest.entry(right).or_default() += 1;
+        }
+        let index = self.buffer.partition_point(|v| *v <= value);
+        self.buffer.insert(index, value);
+    }
+
+    // `false` if there is no such value
+    pub(crate) fn remove(&mut self, value: u32) -> bool {
+        // an equal value in `rest` goes first, the buffer stays as it is
+        if let Some(count) = self.rest.get_mut(&value) {
+            *count -= 1;
+            if *count == 0 {
+                self.rest.remove(&value);
+            }
+            self.len -= 1;
+            return true;
+        }
+        let Ok(index) = self.buffer.binary_search(&value) else {
+            return false;
+        };
+        self.buffer.remove(index);
+        if let Some(mut entry) = self.rest.first_entry() {
+            self.buffer.push(*entry.key());
+            *entry.get_mut() -= 1;
+            if *entry.get() == 0 {
+                entry.remove();
+            }
+        }
+        self.len -= 1;
+        true
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.buffer.clear();
+        self.rest.clear();
+        self.len = 0;
+    }
+}
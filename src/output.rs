@@ -0,0 +1,185 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
+use crate::json::Value;
+use crate::query::Selection;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum OutputFormat {
+    // one value per line, followed by its index with `--indices`
+    Text,
+    Json,
+    // one row per selected value, metadata repeated on every row; a single row with empty rank,
+    // value and index for an empty selection
+    Csv,
+    // one JSON object per selected value, metadata repeated on every line; a single object with the
+    // metadata only for an empty selection
+    Ndjson,
+}
+
+impl OutputFormat {
+    pub fn parse(s: &str) -> Result<Self, String> {
+        match s {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            "csv" => Ok(OutputFormat::Csv),
+            "ndjson" => Ok(OutputFormat::Ndjson),
+            _ => Err(format!(
+                "unknown output format {s:?}, expected text, json, csv or ndjson"
+            )),
+        }
+    }
+}
+
+pub struct Report<'a> {
+    pub selection: &'a Selection,
+    pub strategy: &'a str,
+    // `false` for strategies that may drop values (see `Strategy::exact`)
+    pub exact: bool,
+    pub elapsed: Duration,
+}
+
+impl Report<'_> {
+    fn metadata(&self) -> [(&'static str, Value); 4] {
+        [
+            ("strategy", self.strategy.into()),
+            ("exact", self.exact.into()),
+            ("input_len", self.selection.input_len.into()),
+            ("elapsed_ns", (self.elapsed.as_nanos() as u64).into()),
+        ]
+    }
+
+    pub fn to_json(&self) -> Value {
+        let mut fields = self.metadata().to_vec();
+        fields.push(("values", self.selection.values.clone().into()));
+        if let Some(indices) = &self.selection.indices {
+            fields.push(("indices", indices.clone().into()));
+        }
+        Value::object(fields)
+    }
+
+    fn rows(&self) -> impl Iterator<Item = (usize, u32, Option<u64>)> + '_ {
+        self.selection
+            .values
+            .iter()
+            .enumerate()
+            .map(|(rank, value)| {
+                let index = self.selection.indices.as_ref().map(|indices| indices[rank]);
+                (rank, *value, index)
+            })
+    }
+
+    pub fn write(&self, format: OutputFormat, out: &mut impl Write) -> io::Result<()> {
+        match format {
+            OutputFormat::Text => {
+                for (_, value, index) in self.rows() {
+                    match index {
+                        Some(index) => writeln!(out, "{value}\t{index}")?,
+                        None => writeln!(out, "{value}")?,
+                    }
+                }
+            }
+            OutputFormat::Json => writeln!(out, "{}", self.to_json())?,
+            OutputFormat::Csv => {
+                let with_indices = self.selection.indices.is_some();
+                write!(out, "strategy,exact,input_len,elapsed_ns,rank,value")?;
+                writeln!(out, "{}", if with_indices { ",index" } else { "" })?;
+                let metadata = format!(
+                    "{},{},{},{}",
+                    // strategy names are identifiers, nothing to quote
+                    self.strategy,
+                    self.exact,
+                    self.selection.input_len,
+                    self.elapsed.as_nanos(),
+                );
+                if self.selection.values.is_empty() {
+                    writeln!(out, "{metadata},,{}", if with_indices { "," } else { "" })?;
+                }
+                for (rank, value, index) in self.rows() {
+                    write!(out, "{metadata},{rank},{value}")?;
+                    match index {
+                        Some(index) => writeln!(out, ",{index}")?,
+                        None => writeln!(out)?,
+                    }
+                }
+            }
+            OutputFormat::Ndjson => {
+                if self.selection.values.is_empty() {
+                    writeln!(out, "{}", Value::object(self.metadata().to_vec()))?;
+                }
+                for (rank, value, index) in self.rows() {
+                    let mut fields = self.metadata().to_vec();
+                    fields.push(("rank", rank.into()));
+                    fields.push(("value", value.into()));
+                    if let Some(index) = index {
+                        fields.push(("index", index.into()));
+                    }
+                    writeln!(out, "{}", Value::object(fields))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write(format: OutputFormat, indices: Option<Vec<u64>>) -> String {
+        write_values(format, vec![4, 5], indices)
+    }
+
+    fn write_values(format: OutputFormat, values: Vec<u32>, indices: Option<Vec<u64>>) -> String {
+        let selection = Selection {
+            values,
+            indices,
+            input_len: 10,
+        };
+        let report = Report {
+            selection: &selection,
+            strategy: "stream",
+            exact: true,
+            elapsed: Duration::from_nanos(1500),
+        };
+        let mut out = vec![];
+        report.write(format, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn formats() {
+        assert_eq!("4\n5\n", write(OutputFormat::Text, None));
+        assert_eq!("4\t7\n5\t2\n", write(OutputFormat::Text, Some(vec![7, 2])));
+        assert_eq!(
+            "{\"strategy\":\"stream\",\"exact\":true,\"input_len\":10,\"elapsed_ns\":1500,\"values\":[4,5],\"indices\":[7,2]}\n",
+            write(OutputFormat::Json, Some(vec![7, 2]))
+        );
+        assert_eq!(
+            "strategy,exact,input_len,elapsed_ns,rank,value\nstream,true,10,1500,0,4\nstream,true,10,1500,1,5\n",
+            write(OutputFormat::Csv, None)
+        );
+        assert_eq!(
+            "{\"strategy\":\"stream\",\"exact\":true,\"input_len\":10,\"elapsed_ns\":1500,\"rank\":0,\"value\":4,\"index\":7}\n\
+             {\"strategy\":\"stream\",\"exact\":true,\"input_len\":10,\"elapsed_ns\":1500,\"rank\":1,\"value\":5,\"index\":2}\n",
+            write(OutputFormat::Ndjson, Some(vec![7, 2]))
+        );
+    }
+
+    #[test]
+    fn empty_selection_keeps_metadata() {
+        assert_eq!("", write_values(OutputFormat::Text, vec![], None));
+        assert_eq!(
+            "strategy,exact,input_len,elapsed_ns,rank,value\nstream,true,10,1500,,\n",
+            write_values(OutputFormat::Csv, vec![], None)
+        );
+        assert_eq!(
+            "strategy,exact,input_len,elapsed_ns,rank,value,index\nstream,true,10,1500,,,\n",
+            write_values(OutputFormat::Csv, vec![], Some(vec![]))
+        );
+        assert_eq!(
+            "{\"strategy\":\"stream\",\"exact\":true,\"input_len\":10,\"elapsed_ns\":1500}\n",
+            write_values(OutputFormat::Ndjson, vec![], Some(vec![]))
+        );
+    }
+}
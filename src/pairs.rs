@@ -0,0 +1,89 @@
+use alloc::vec::Vec;
+
+use crate::accumulator::Accumulator;
+use crate::error::Least8Error;
+use crate::implementation::CAPACITY;
+
+// the 8 smallest keys as an `Accumulator` selects them, with the input position of every key kept
+// in step; only keys are compared and only the selected payloads are cloned, so payloads need no
+// ordering, and of equal keys the earliest one keeps its payload
+fn select(keys: impl Iterator<Item = u32>) -> Accumulator {
+    let mut accu = Accumulator::new(CAPACITY);
+    for key in keys {
+        accu.push(key);
+    }
+    accu
+}
+
+// the 8 smallest keys with their payloads, ascending by key
+pub fn least_8_pairs<V: Clone>(l: &[(u32, V)]) -> Vec<(u32, V)> {
+    let accu = select(l.iter().map(|(key, _)| *key));
+    accu.indices()
+        .iter()
+        .map(|index| l[*index as usize].clone())
+        .collect()
+}
+
+// the same for keys and payloads in separate slices of the same length, returned the same way
+pub fn least_8_with<V: Clone>(
+    keys: &[u32],
+    values: &[V],
+) -> Result<(Vec<u32>, Vec<V>), Least8Error> {
+    if keys.len() != values.len() {
+        return Err(Least8Error::LengthMismatch {
+            keys: keys.len(),
+            values: values.len(),
+        });
+    }
+    let accu = select(keys.iter().copied());
+    let payloads = accu
+        .indices()
+        .iter()
+        .map(|index| values[*index as usize].clone())
+        .collect();
+    Ok((accu.values(), payloads))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{implementation, make_list};
+
+    #[test]
+    fn payloads_follow_keys() {
+        let keys = make_list();
+        let ids: Vec<usize> = (0..keys.len()).collect();
+        let (least, positions) = least_8_with(&keys, &ids).unwrap();
+        assert_eq!(implementation::naive(&keys), least);
+        for (key, position) in least.iter().zip(&positions) {
+            assert_eq!(*key, keys[*position]);
+        }
+
+        let pairs: Vec<(u32, usize)> = keys.iter().copied().zip(ids).collect();
+        assert_eq!(
+            least.into_iter().zip(positions).collect::<Vec<_>>(),
+            least_8_pairs(&pairs)
+        );
+
+        assert_eq!(
+            Err(Least8Error::LengthMismatch { keys: 3, values: 2 }),
+            least_8_with(&[1, 2, 3], &["a", "b"])
+        );
+    }
+
+    #[test]
+    fn earliest_of_equal_keys_wins() {
+        let l: Vec<(u32, &str)> = (0..20)
+            .map(|i| (i % 3, ["a", "b", "c", "d", "e", "f", "g"][i as usize % 7]))
+            .collect();
+        let mut expected = l.clone();
+        // stable, so equal keys keep input order
+        expected.sort_by_key(|(key, _)| *key);
+        expected.truncate(CAPACITY);
+        assert_eq!(expected, least_8_pairs(&l));
+
+        let short = [(2, "x"), (1, "y")];
+        assert_eq!(vec![(1, "y"), (2, "x")], least_8_pairs(&short));
+        assert_eq!(Vec::<(u32, ())>::new(), least_8_pairs(&[]));
+    }
+}
@@ -0,0 +1,104 @@
+use alloc::{format, string::String, vec::Vec};
+
+use crate::accumulator::Accumulator;
+use crate::implementation::Strategy;
+
+pub const DEFAULT_K: usize = 8;
+
+// what to select and how, shared by the command line and the servers
+#[derive(Clone, Copy)]
+pub struct Query {
+    pub k: usize,
+    // `None` streams values through an `Accumulator`, which supports every option below
+    pub strategy: Option<&'static Strategy>,
+    pub largest: bool,
+    pub distinct: bool,
+    pub indices: bool,
+}
+
+impl Default for Query {
+    fn default() -> Self {
+        Self {
+            k: DEFAULT_K,
+            strategy: None,
+            largest: false,
+            distinct: false,
+            indices: false,
+        }
+    }
+}
+
+impl Query {
+    pub fn strategy_name(&self) -> &'static str {
+        self.strategy.map_or("stream", |strategy| strategy.name)
+    }
+
+    pub fn is_exact(&self) -> bool {
+        self.strategy.is_none_or(|strategy| strategy.exact)
+    }
+
+    // the registered strategies only know the 8 smallest values
+    pub fn check(&self) -> Result<(), String> {
+        if let Some(strategy) = self.strategy {
+            if self.k != DEFAULT_K || self.largest || self.distinct || self.indices {
+                return Err(format!(
+                    "strategy {} only selects the {DEFAULT_K} smallest values, \
+                     k/largest/distinct/indices need the stream strategy",
+                    strategy.name
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn accumulator(&self) -> Accumulator {
+        Accumulator::new(self.k)
+            .largest(self.largest)
+            .distinct(self.distinct)
+    }
+
+    // the registered strategies return fewer values than asked for otherwise
+    pub fn check_len(&self, len: u64) -> Result<(), String> {
+        match self.strategy {
+            Some(strategy) if len < DEFAULT_K as u64 => Err(format!(
+                "strategy {} needs at least {DEFAULT_K} values, got {len}",
+                strategy.name
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn run(&self, l: &[u32]) -> Result<Selection, String> {
+        self.check()?;
+        self.check_len(l.len() as u64)?;
+        match self.strategy {
+            Some(strategy) => Ok(Selection {
+                values: (strategy.func)(l),
+                indices: None,
+                input_len: l.len() as u64,
+            }),
+            None => {
+                let mut accu = self.accumulator();
+                accu.push_many(l);
+                Ok(self.selection(&accu))
+            }
+        }
+    }
+
+    pub fn selection(&self, accu: &Accumulator) -> Selection {
+        Selection {
+            values: accu.values(),
+            indices: self.indices.then(|| accu.indices().to_vec()),
+            input_len: accu.pushed(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Selection {
+    pub values: Vec<u32>,
+    // zero-based input positions of `values`, when asked for
+    pub indices: Option<Vec<u64>>,
+    // count of values the selection was made from
+    pub input_len: u64,
+}
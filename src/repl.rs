@@ -0,0 +1,282 @@
+use std::fs::File;
+use std::io::{self, BufRead, Write};
+
+use crate::format::{self, Format};
+use crate::implementation;
+use crate::query::Query;
+
+pub const HELP: &str = "\
+commands:
+  load FILE [FORMAT]   append every value of a file (text by default)
+  push V1 V2 ...       append values
+  clear                forget every value
+  strategy NAME        stream or one of implementation::STRATEGIES
+  k N                  how many values to select
+  largest [on|off]     select the largest values, toggles without argument
+  distinct [on|off]    skip values equal to an already selected one
+  indices [on|off]     also show the input position of each value
+  show                 the current selection
+  threshold            the value a new one has to beat to get selected
+  status               the current settings
+  history              the commands entered so far, rerun one with !N
+  help                 this text
+  quit                 leave";
+
+// an interactive session: values loaded so far plus the query to run on them
+pub struct Repl {
+    query: Query,
+    values: Vec<u32>,
+    history: Vec<String>,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self {
+            query: Query::default(),
+            values: vec![],
+            history: vec![],
+        }
+    }
+
+    pub fn query(&self) -> &Query {
+        &self.query
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    // reads commands until `quit` or the end of input, prompting only when asked to
+    pub fn run(
+        &mut self,
+        input: impl BufRead,
+        out: &mut impl Write,
+        prompt: bool,
+    ) -> io::Result<()> {
+        if prompt {
+            write!(out, "> ")?;
+            out.flush()?;
+        }
+        for line in input.lines() {
+            let line = line?;
+            let line = line.trim();
+            if line.eq_ignore_ascii_case("quit") || line.eq_ignore_ascii_case("exit") {
+                break;
+            }
+            if !line.is_empty() {
+                match self.execute(line) {
+                    Ok(reply) if reply.is_empty() => {}
+                    Ok(reply) => writeln!(out, "{reply}")?,
+                    Err(err) => writeln!(out, "error: {err}")?,
+                }
+            }
+            if prompt {
+                write!(out, "> ")?;
+                out.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    // the reply to a single command, recording it in the history
+    pub fn execute(&mut self, line: &str) -> Result<String, String> {
+        let line = match line.strip_prefix('!') {
+            Some(n) => {
+                let n: usize = n
+                    .parse()
+                    .map_err(|_| format!("invalid history entry {n:?}"))?;
+                n.checked_sub(1)
+                    .and_then(|i| self.history.get(i))
+                    .ok_or(format!("no history entry {n}"))?
+                    .clone()
+            }
+            None => line.to_owned(),
+        };
+        self.history.push(line.clone());
+        self.try_execute(&line)
+    }
+
+    fn try_execute(&mut self, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().ok_or("empty command")?.to_ascii_lowercase();
+        let mut flag = |current: bool| match words.next() {
+            None => Ok(!current),
+            Some("on" | "true" | "1") => Ok(true),
+            Some("off" | "false" | "0") => Ok(false),
+            Some(word) => Err(format!("expected on or off, got {word:?}")),
+        };
+        match command.as_str() {
+            "load" => {
+                let path = words.next().ok_or("load needs a file")?;
+                let format = words.next().map_or(Ok(Format::Text), Format::parse)?;
+                let file = File::open(path).map_err(|err| format!("{path}: {err}"))?;
+                let len = self.values.len();
+                format::decode(&format, path, file, |value| self.values.push(value))?;
+                Ok(format!(
+                    "loaded {} values, {} in total",
+                    self.values.len() - len,
+                    self.values.len()
+                ))
+            }
+            "push" => {
+                let values = words
+                    .map(|word| word.parse().map_err(|_| format!("invalid number {word:?}")))
+                    .collect::<Result<Vec<u32>, _>>()?;
+                self.values.extend_from_slice(&values);
+                Ok(format!("{} values in total", self.values.len()))
+            }
+            "clear" => {
+                self.values.clear();
+                Ok(String::new())
+            }
+            "strategy" => {
+                let name = words.next().ok_or("strategy needs a name")?;
+                self.query.strategy = match name {
+                    "stream" => None,
+                    _ => Some(
+                        implementation::strategy(name)
+                            .ok_or(format!("unknown strategy {name:?}"))?,
+                    ),
+                };
+                Ok(String::new())
+            }
+            "k" => {
+                let k = words.next().ok_or("k needs a number")?;
+                self.query.k = k.parse().map_err(|_| format!("invalid k {k:?}"))?;
+                Ok(String::new())
+            }
+            "largest" => {
+                self.query.largest = flag(self.query.largest)?;
+                Ok(String::new())
+            }
+            "distinct" => {
+                self.query.distinct = flag(self.query.distinct)?;
+                Ok(String::new())
+            }
+            "indices" => {
+                self.query.indices = flag(self.query.indices)?;
+                Ok(String::new())
+            }
+            "show" => {
+                let selection = self.query.run(&self.values)?;
+                Ok(match &selection.indices {
+                    Some(indices) => selection
+                        .values
+                        .iter()
+                        .zip(indices)
+                        .map(|(value, index)| format!("{value}\t{index}"))
+                        .collect::<Vec<_>>()
+                        .join("\n"),
+                    None => selection
+                        .values
+                        .iter()
+                        .map(u32::to_string)
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                })
+            }
+            "threshold" => {
+                self.query.check()?;
+                let mut accu = self.query.accumulator();
+                accu.push_many(&self.values);
+                Ok(match accu.threshold() {
+                    Some(threshold) => threshold.to_string(),
+                    None => format!("none, fewer than {} values", self.query.k),
+                })
+            }
+            "status" => Ok(format!(
+                "strategy {} k {} largest {} distinct {} indices {}, {} values",
+                self.query.strategy_name(),
+                self.query.k,
+                self.query.largest,
+                self.query.distinct,
+                self.query.indices,
+                self.values.len()
+            )),
+            "history" => Ok(self
+                .history
+                .iter()
+                .enumerate()
+                .map(|(i, line)| format!("{:>4}  {line}", i + 1))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "help" => Ok(HELP.to_owned()),
+            _ => Err(format!("unknown command {command:?}, try help")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::make_list;
+
+    #[test]
+    fn session() {
+        let mut repl = Repl::new();
+        assert_eq!(
+            Ok("5 values in total".to_owned()),
+            repl.execute("push 9 3 7 1 3")
+        );
+        assert_eq!(Ok("1 3 3 7 9".to_owned()), repl.execute("show"));
+        repl.execute("k 2").unwrap();
+        assert_eq!(Ok("3".to_owned()), repl.execute("threshold"));
+        repl.execute("largest").unwrap();
+        assert_eq!(Ok("9 7".to_owned()), repl.execute("show"));
+        repl.execute("largest off").unwrap();
+        repl.execute("distinct on").unwrap();
+        repl.execute("k 3").unwrap();
+        assert_eq!(Ok("1 3 7".to_owned()), repl.execute("show"));
+        repl.execute("indices").unwrap();
+        assert_eq!(Ok("1\t3\n3\t1\n7\t2".to_owned()), repl.execute("show"));
+
+        // registered strategies only know the 8 smallest
+        repl.execute("strategy optimized").unwrap();
+        assert!(repl.execute("show").is_err());
+        assert!(repl.execute("strategy bogus").is_err());
+        assert!(repl.execute("frob").is_err());
+
+        assert_eq!("push 9 3 7 1 3", repl.history()[0]);
+        repl.execute("strategy stream").unwrap();
+        assert_eq!(repl.execute("show"), repl.execute("!2"));
+        assert_eq!("show", repl.history().last().unwrap());
+        assert!(repl.execute("!100").is_err());
+    }
+
+    #[test]
+    fn run_reads_lines() {
+        let l = make_list();
+        let path = std::env::temp_dir().join(format!("least_8-{}-repl", std::process::id()));
+        format::encode(&Format::LeU32, &l, File::create(&path).unwrap()).unwrap();
+
+        let input = format!(
+            "load {} le-u32\nstrategy non_cheat_optimized\nshow\nstatus\nquit\nshow\n",
+            path.display()
+        );
+        let mut out = vec![];
+        let mut repl = Repl::new();
+        repl.run(input.as_bytes(), &mut out, false).unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let expected: Vec<_> = implementation::naive(&l)
+            .iter()
+            .map(u32::to_string)
+            .collect();
+        assert_eq!(
+            format!(
+                "loaded {len} values, {len} in total\n{}\n\
+                 strategy non_cheat_optimized k 8 largest false distinct false indices false, {len} values\n",
+                expected.join(" "),
+                len = l.len()
+            ),
+            String::from_utf8(out).unwrap()
+        );
+        assert_eq!(4, repl.history().len());
+    }
+}
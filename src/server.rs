@@ -0,0 +1,237 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::accumulator::Accumulator;
+use crate::format::{self, Format};
+
+pub const DEFAULT_CAPACITY: usize = 64;
+// the largest `--capacity`, every accumulator may end up holding this many values
+pub const MAX_CAPACITY: usize = 1 << 20;
+
+pub const HELP: &str = "\
+commands, one per line, answered with `OK ...` or `ERR message`:
+  LOAD name file [format]   push every value of a file (text by default)
+  PUSH name v1 v2 ...       push values
+  QUERY name k              the k smallest values pushed so far
+  RESET name                forget the accumulator
+  QUIT                      close the connection";
+
+// named accumulators shared by every connection
+pub struct State {
+    // every accumulator keeps this many values, the largest k a query may ask for
+    capacity: usize,
+    accumulators: Mutex<HashMap<String, Accumulator>>,
+}
+
+impl State {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            accumulators: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn with_accumulator<T>(&self, name: &str, f: impl FnOnce(&mut Accumulator) -> T) -> T {
+        let mut accumulators = self.accumulators.lock().unwrap();
+        let accu = accumulators
+            .entry(name.to_owned())
+            .or_insert_with(|| Accumulator::new(self.capacity));
+        f(accu)
+    }
+
+    // the reply to a single command line, without the trailing newline
+    pub fn execute(&self, line: &str) -> String {
+        match self.try_execute(line) {
+            Ok(reply) if reply.is_empty() => "OK".to_owned(),
+            Ok(reply) => format!("OK {reply}"),
+            Err(err) => format!("ERR {err}"),
+        }
+    }
+
+    fn try_execute(&self, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let command = words.next().ok_or("empty command")?;
+        let mut name = || words.next().ok_or(format!("{command} needs a name"));
+        match command.to_ascii_uppercase().as_str() {
+            "LOAD" => {
+                let name = name()?;
+                let path = words.next().ok_or("LOAD needs a file")?;
+                let format = words.next().map_or(Ok(Format::Text), Format::parse)?;
+                let file = File::open(path).map_err(|err| format!("{path}: {err}"))?;
+                // decoded without holding the lock, then merged
+                let mut loaded = Accumulator::new(self.capacity);
+                format::decode(&format, path, file, |value| loaded.push(value))?;
+                self.with_accumulator(name, |accu| accu.merge(&loaded));
+                Ok(loaded.pushed().to_string())
+            }
+            "PUSH" => {
+                let name = name()?;
+                let values = words
+                    .map(|word| word.parse().map_err(|_| format!("invalid number {word:?}")))
+                    .collect::<Result<Vec<u32>, _>>()?;
+                self.with_accumulator(name, |accu| accu.push_many(&values));
+                Ok(values.len().to_string())
+            }
+            "QUERY" => {
+                let name = name()?;
+                let k: usize = match words.next() {
+                    Some(k) => k.parse().map_err(|_| format!("invalid k {k:?}"))?,
+                    None => return Err("QUERY needs k".to_owned()),
+                };
+                if k > self.capacity {
+                    return Err(format!("k must not exceed {}", self.capacity));
+                }
+                let accumulators = self.accumulators.lock().unwrap();
+                let accu = accumulators
+                    .get(name)
+                    .ok_or(format!("no accumulator {name:?}"))?;
+                Ok(accu
+                    .values()
+                    .iter()
+                    .take(k)
+                    .map(u32::to_string)
+                    .collect::<Vec<_>>()
+                    .join(" "))
+            }
+            "RESET" => {
+                let name = name()?;
+                self.accumulators.lock().unwrap().remove(name);
+                Ok(String::new())
+            }
+            "HELP" => Ok(HELP.replace('\n', " | ")),
+            _ => Err(format!("unknown command {command:?}, try HELP")),
+        }
+    }
+}
+
+pub struct Server {
+    listener: TcpListener,
+    state: Arc<State>,
+}
+
+impl Server {
+    pub fn bind(addr: impl ToSocketAddrs, capacity: usize) -> io::Result<Self> {
+        Ok(Self {
+            listener: TcpListener::bind(addr)?,
+            state: Arc::new(State::new(capacity)),
+        })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    // serves every connection on its own thread, forever
+    pub fn run(self) -> io::Result<()> {
+        for stream in self.listener.incoming() {
+            let stream = stream?;
+            let state = self.state.clone();
+            thread::spawn(move || {
+                // a client going away is not the server's problem
+                let _ = handle_connection(stream, &state);
+            });
+        }
+        Ok(())
+    }
+}
+
+fn handle_connection(stream: TcpStream, state: &State) -> io::Result<()> {
+    let mut writer = stream.try_clone()?;
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.trim().eq_ignore_ascii_case("QUIT") {
+            writeln!(writer, "OK")?;
+            break;
+        }
+        writeln!(writer, "{}", state.execute(&line))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{implementation, make_list};
+
+    struct Client {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+    }
+
+    impl Client {
+        fn connect(addr: SocketAddr) -> Self {
+            let writer = TcpStream::connect(addr).unwrap();
+            Self {
+                reader: BufReader::new(writer.try_clone().unwrap()),
+                writer,
+            }
+        }
+
+        fn send(&mut self, line: &str) -> String {
+            writeln!(self.writer, "{line}").unwrap();
+            let mut reply = String::new();
+            self.reader.read_line(&mut reply).unwrap();
+            reply.trim_end().to_owned()
+        }
+    }
+
+    fn start() -> SocketAddr {
+        let server = Server::bind("127.0.0.1:0", DEFAULT_CAPACITY).unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        addr
+    }
+
+    #[test]
+    fn line_protocol() {
+        let addr = start();
+        let mut client = Client::connect(addr);
+        assert_eq!("OK 4", client.send("PUSH a 9 3 7 1"));
+        assert_eq!("OK 1 3", client.send("QUERY a 2"));
+        assert_eq!("OK 1", client.send("push a 0"));
+        assert_eq!("OK 0 1 3 7 9", client.send("QUERY a 8"));
+
+        // another connection sees the same accumulators
+        let mut other = Client::connect(addr);
+        assert_eq!("OK 0 1 3", other.send("QUERY a 3"));
+        assert_eq!("OK", other.send("RESET a"));
+        assert!(client.send("QUERY a 3").starts_with("ERR no accumulator"));
+
+        assert!(client.send("PUSH a x").starts_with("ERR invalid number"));
+        assert!(client
+            .send("QUERY a 1000")
+            .starts_with("ERR k must not exceed"));
+        assert!(client.send("FROB").starts_with("ERR unknown command"));
+        assert_eq!("OK", client.send("QUIT"));
+    }
+
+    #[test]
+    fn load_file() {
+        let l = make_list();
+        let path = std::env::temp_dir().join(format!("least_8-{}-serve", std::process::id()));
+        format::encode(&Format::LeU32, &l, File::create(&path).unwrap()).unwrap();
+
+        let mut client = Client::connect(start());
+        let reply = client.send(&format!("LOAD list {} le-u32", path.display()));
+        assert_eq!(format!("OK {}", l.len()), reply);
+        let expected: Vec<_> = implementation::naive(&l)
+            .iter()
+            .map(u32::to_string)
+            .collect();
+        assert_eq!(
+            format!("OK {}", expected.join(" ")),
+            client.send("QUERY list 8")
+        );
+        assert!(client
+            .send("LOAD list /nonexistent/file")
+            .starts_with("ERR /nonexistent/file"));
+        std::fs::remove_file(path).unwrap();
+    }
+}
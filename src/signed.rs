@@ -0,0 +1,166 @@
+use alloc::vec::Vec;
+
+use crate::implementation;
+
+mod sealed {
+    pub trait Sealed {}
+    impl Sealed for i8 {}
+    impl Sealed for i16 {}
+    impl Sealed for i32 {}
+    impl Sealed for i64 {}
+}
+
+// flipping the sign bit maps MIN..=MAX onto 0..=MAX of the unsigned type of the same width in
+// order, so the packed stores can select the unsigned keys and flip the bit back on output
+pub trait Signed: Copy + sealed::Sealed {
+    // the smallest values, ascending, selected by the narrowest exact packed store that fits
+    fn least_8(l: &[Self]) -> Vec<Self>;
+}
+
+impl Signed for i8 {
+    fn least_8(l: &[Self]) -> Vec<Self> {
+        implementation::less_cheat_optimized_keys(
+            l.iter().map(|value| (*value as u8 ^ 1 << 7) as u16),
+        )
+        .iter()
+        .map(|key| (*key as u8 ^ 1 << 7) as i8)
+        .collect()
+    }
+}
+
+impl Signed for i16 {
+    fn least_8(l: &[Self]) -> Vec<Self> {
+        implementation::less_cheat_optimized_keys(l.iter().map(|value| *value as u16 ^ 1 << 15))
+            .iter()
+            .map(|key| (*key as u16 ^ 1 << 15) as i16)
+            .collect()
+    }
+}
+
+impl Signed for i32 {
+    fn least_8(l: &[Self]) -> Vec<Self> {
+        implementation::non_cheat_optimized_keys(l.iter().map(|value| *value as u32 ^ 1 << 31))
+            .iter()
+            .map(|key| (*key ^ 1 << 31) as i32)
+            .collect()
+    }
+}
+
+impl Signed for i64 {
+    fn least_8(l: &[Self]) -> Vec<Self> {
+        implementation::non_cheat_optimized_u64_keys(l.iter().map(|value| *value as u64 ^ 1 << 63))
+            .iter()
+            .map(|key| (*key ^ 1 << 63) as i64)
+            .collect()
+    }
+}
+
+pub fn least_8<T: Signed>(l: &[T]) -> Vec<T> {
+    T::least_8(l)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn expected<T: Ord + Copy>(l: &[T]) -> Vec<T> {
+        let mut sorted = l.to_vec();
+        sorted.sort();
+        sorted.truncate(implementation::CAPACITY);
+        sorted
+    }
+
+    // every subset of the values around the edges, in every rotation
+    fn check_edges<T: Signed + Ord + core::fmt::Debug>(edges: &[T]) {
+        for mask in 0..1u32 << edges.len() {
+            let mut l: Vec<T> = edges
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| mask & 1 << i != 0)
+                .map(|(_, value)| *value)
+                .collect();
+            for _ in 0..l.len().max(1) {
+                assert_eq!(expected(&l), least_8(&l), "{l:?}");
+                if !l.is_empty() {
+                    l.rotate_left(1);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn edges() {
+        check_edges(&[
+            i8::MIN,
+            i8::MIN + 1,
+            -2,
+            -1,
+            0,
+            1,
+            i8::MAX - 1,
+            i8::MAX,
+            -1,
+            0,
+        ]);
+        check_edges(&[
+            i16::MIN,
+            i16::MIN + 1,
+            -2,
+            -1,
+            0,
+            1,
+            i16::MAX - 1,
+            i16::MAX,
+            i16::MIN,
+        ]);
+        check_edges(&[
+            i32::MIN,
+            i32::MIN + 1,
+            -2,
+            -1,
+            0,
+            1,
+            i32::MAX - 1,
+            i32::MAX,
+            i32::MAX,
+        ]);
+        check_edges(&[
+            i64::MIN,
+            i64::MIN + 1,
+            i64::from(i32::MIN),
+            -1,
+            0,
+            1 << 32,
+            i64::MAX - 1,
+            i64::MAX,
+            i64::MIN,
+        ]);
+    }
+
+    #[test]
+    fn every_value() {
+        let l: Vec<i8> = (i8::MIN..=i8::MAX).rev().collect();
+        assert_eq!(expected(&l), least_8(&l));
+        let l: Vec<i8> = (i8::MIN..=i8::MAX)
+            .map(|value| value.rotate_left(3))
+            .collect();
+        assert_eq!(expected(&l), least_8(&l));
+
+        let l: Vec<i16> = (i16::MIN..=i16::MAX).rev().collect();
+        assert_eq!(expected(&l), least_8(&l));
+        let l: Vec<i16> = (i16::MIN..=i16::MAX)
+            .map(|value| value.rotate_left(7))
+            .collect();
+        assert_eq!(expected(&l), least_8(&l));
+
+        let l: Vec<i32> = (i16::MIN..=i16::MAX)
+            .map(|value| i32::from(value).rotate_left(16) ^ 0x5555)
+            .collect();
+        assert_eq!(expected(&l), least_8(&l));
+
+        let l: Vec<i64> = (i16::MIN..=i16::MAX)
+            .map(|value| i64::from(value).rotate_left(48) ^ 0x5555_5555)
+            .collect();
+        assert_eq!(expected(&l), least_8(&l));
+    }
+}
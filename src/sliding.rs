@@ -0,0 +1,112 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::multiset::Least8Set;
+
+// the 8 smallest of the last `window` values pushed, kept up to date on every push
+#[derive(Clone, Debug)]
+pub struct SlidingLeast8 {
+    window: usize,
+    // oldest first, to know what expires next
+    values: VecDeque<u32>,
+    set: Least8Set,
+}
+
+impl SlidingLeast8 {
+    pub fn new(window: usize) -> Self {
+        Self {
+            window,
+            // `window` may be anything a caller asks for, the deque grows with what is pushed
+            values: VecDeque::new(),
+            set: Least8Set::default(),
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    // count of values in the window, at most `window`
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.len() == 0
+    }
+
+    // adds `value` and evicts the oldest one once the window is full
+    pub fn push(&mut self, value: u32) {
+        self.values.push_back(value);
+        self.set.insert(value);
+        while self.values.len() > self.window {
+            if let Some(expired) = self.values.pop_front() {
+                self.set.remove(expired);
+            }
+        }
+    }
+
+    pub fn push_many(&mut self, values: &[u32]) {
+        for value in values {
+            self.push(*value);
+        }
+    }
+
+    // ascending, like `implementation::optimized` over the window
+    pub fn least_8(&self) -> Vec<u32> {
+        self.set.least_8().to_vec()
+    }
+
+    pub fn clear(&mut self) {
+        self.values.clear();
+        self.set.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{implementation, make_list};
+
+    #[test]
+    fn same_as_optimized_over_window() {
+        // few distinct values, so equal values expire while others stay selected
+        let l: Vec<u32> = make_list()[..3_000]
+            .iter()
+            .map(|value| value % 50)
+            .collect();
+        for window in [0, 1, 7, 8, 9, 100] {
+            let mut sliding = SlidingLeast8::new(window);
+            for (i, value) in l.iter().enumerate() {
+                sliding.push(*value);
+                let start = (i + 1).saturating_sub(window);
+                assert_eq!(
+                    implementation::optimized(&l[start..=i]),
+                    sliding.least_8(),
+                    "window {window} at {i}"
+                );
+                assert_eq!(i + 1 - start, sliding.len());
+            }
+        }
+    }
+
+    #[test]
+    fn ascending_and_descending() {
+        let mut sliding = SlidingLeast8::new(10);
+        sliding.push_many(&(0..100).collect::<Vec<_>>());
+        assert_eq!((90..98).collect::<Vec<_>>(), sliding.least_8());
+        sliding.push_many(&(0..100).rev().collect::<Vec<_>>());
+        assert_eq!((0..8).collect::<Vec<_>>(), sliding.least_8());
+        sliding.clear();
+        assert!(sliding.is_empty());
+        assert!(sliding.least_8().is_empty());
+    }
+
+    #[test]
+    fn huge_window() {
+        let mut sliding = SlidingLeast8::new(usize::MAX);
+        sliding.push_many(&[3, 1, 2]);
+        assert_eq!(vec![1, 2, 3], sliding.least_8());
+        assert_eq!(3, sliding.len());
+    }
+}
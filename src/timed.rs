@@ -0,0 +1,136 @@
+use alloc::collections::VecDeque;
+use alloc::vec::Vec;
+
+use crate::multiset::Least8Set;
+
+// the 8 smallest values pushed within the last `duration` before the caller-supplied `now`;
+// timestamps and `duration` are in whatever unit the caller counts in
+#[derive(Clone, Debug)]
+pub struct TimedLeast8 {
+    duration: u64,
+    // oldest first, by timestamp
+    values: VecDeque<(u64, u32)>,
+    set: Least8Set,
+    // everything at or before it has expired
+    expired: Option<u64>,
+}
+
+impl TimedLeast8 {
+    pub fn new(duration: u64) -> Self {
+        Self {
+            duration,
+            values: VecDeque::new(),
+            set: Least8Set::default(),
+            expired: None,
+        }
+    }
+
+    pub fn duration(&self) -> u64 {
+        self.duration
+    }
+
+    // count of values not expired yet
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.set.len() == 0
+    }
+
+    // timestamps may come out of order; a value already expired when it arrives is dropped
+    pub fn push(&mut self, timestamp: u64, value: u32) {
+        if self.expired.is_some_and(|expired| timestamp <= expired) {
+            return;
+        }
+        let index = if self
+            .values
+            .back()
+            .is_none_or(|(last, _)| *last <= timestamp)
+        {
+            self.values.len()
+        } else {
+            self.values.partition_point(|(t, _)| *t <= timestamp)
+        };
+        self.values.insert(index, (timestamp, value));
+        self.set.insert(value);
+    }
+
+    // evicts every value pushed at or before `now - duration`; `now` never goes back
+    pub fn expire(&mut self, now: u64) {
+        let Some(horizon) = now.checked_sub(self.duration) else {
+            return;
+        };
+        if self.expired.is_some_and(|expired| horizon <= expired) {
+            return;
+        }
+        self.expired = Some(horizon);
+        while let Some((timestamp, value)) = self.values.front() {
+            if *timestamp > horizon {
+                break;
+            }
+            self.set.remove(*value);
+            self.values.pop_front();
+        }
+    }
+
+    // ascending, over the values left by the last `expire`
+    pub fn least_8(&self) -> Vec<u32> {
+        self.set.least_8().to_vec()
+    }
+
+    // `expire(now)`, then `least_8()`
+    pub fn least_8_at(&mut self, now: u64) -> Vec<u32> {
+        self.expire(now);
+        self.least_8()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{implementation, make_list};
+
+    #[test]
+    fn same_as_optimized_over_duration() {
+        // synthetic timestamps: several values per tick and some ticks without any
+        let l = make_list();
+        let samples: Vec<(u64, u32)> = l[..2_000]
+            .iter()
+            .enumerate()
+            .map(|(i, value)| ((i as u64 / 3) * 2, value % 100))
+            .collect();
+        let duration = 60;
+        let mut timed = TimedLeast8::new(duration);
+        for (i, (timestamp, value)) in samples.iter().enumerate() {
+            timed.push(*timestamp, *value);
+            let now = timestamp + 1;
+            let in_window: Vec<u32> = samples[..=i]
+                .iter()
+                .filter(|(t, _)| *t + duration > now)
+                .map(|(_, value)| *value)
+                .collect();
+            assert_eq!(implementation::optimized(&in_window), timed.least_8_at(now));
+            assert_eq!(in_window.len(), timed.len());
+        }
+        timed.expire(u64::MAX);
+        assert!(timed.is_empty());
+    }
+
+    #[test]
+    fn out_of_order_and_late() {
+        let mut timed = TimedLeast8::new(10);
+        timed.push(20, 5);
+        timed.push(12, 1);
+        timed.push(15, 3);
+        assert_eq!(vec![1, 3, 5], timed.least_8_at(21));
+        assert_eq!(vec![3, 5], timed.least_8_at(22));
+        // at or before the horizon of the last `expire`
+        timed.push(12, 0);
+        assert_eq!(vec![3, 5], timed.least_8());
+        // `now` going back doesn't bring anything back
+        assert_eq!(vec![3, 5], timed.least_8_at(0));
+        assert_eq!(vec![5], timed.least_8_at(25));
+        assert_eq!(Vec::<u32>::new(), timed.least_8_at(30));
+    }
+}
@@ -0,0 +1,58 @@
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+
+use least_8::implementation::{CAPACITY, STRATEGIES};
+use least_8::make_list;
+
+// counts the allocations made on the thread that asked for counting
+struct Counting;
+
+thread_local! {
+    static COUNT: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        COUNT.with(|count| count.set(count.get().map(|count| count + 1)));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    COUNT.with(|count| count.set(Some(0)));
+    f();
+    COUNT.with(|count| count.replace(None)).unwrap()
+}
+
+// every `_into` variant but `thread_optimized_into` is allocation-free; that one allocates for
+// its threads only, as many times whatever the input length
+#[test]
+fn into_variants_do_not_allocate() {
+    let l = make_list();
+    let short = &l[..100];
+    for strategy in STRATEGIES {
+        let mut out = [0; CAPACITY];
+        let mut expected = (strategy.func)(&l);
+        let mut len = 0;
+        let count = allocations(|| len = (strategy.into)(&l, &mut out));
+        assert_eq!(expected, out[..len], "{}", strategy.name);
+        if strategy.name == "thread_optimized" {
+            // std allocates for every thread spawned, but not for the values
+            let short_count = allocations(|| len = (strategy.into)(short, &mut out));
+            assert_eq!(short_count, count, "{}", strategy.name);
+            continue;
+        }
+        assert_eq!(0, count, "{}", strategy.name);
+
+        expected = (strategy.func)(short);
+        assert_eq!(0, allocations(|| len = (strategy.into)(short, &mut out)));
+        assert_eq!(expected, out[..len], "{}", strategy.name);
+    }
+}
@@ -0,0 +1,150 @@
+use std::io::{Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpStream};
+use std::thread;
+
+use least_8::http::HttpServer;
+use least_8::implementation::{self, STRATEGIES};
+use least_8::json::{self, Value};
+use least_8::make_list;
+
+fn start() -> SocketAddr {
+    let server = HttpServer::bind("127.0.0.1:0").unwrap();
+    let addr = server.local_addr().unwrap();
+    thread::spawn(move || server.run());
+    addr
+}
+
+// sends one request and returns the status code and the parsed JSON body
+fn request(addr: SocketAddr, head: &str, body: &[u8]) -> (u16, Value) {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "{head}\r\nContent-Length: {}\r\n\r\n", body.len()).unwrap();
+    stream.write_all(body).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let (head, body) = response.split_once("\r\n\r\n").unwrap();
+    let status = head.split(' ').nth(1).unwrap().parse().unwrap();
+    (status, json::parse(body).unwrap())
+}
+
+fn values(value: &Value, key: &str) -> Vec<u64> {
+    value
+        .get(key)
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|value| value.as_u64().unwrap())
+        .collect()
+}
+
+#[test]
+fn least_text_body() {
+    let addr = start();
+    let (status, body) = request(
+        addr,
+        "POST /least?k=3 HTTP/1.1\r\nHost: localhost",
+        b"9\n3\n7\n1\n5\n",
+    );
+    assert_eq!(200, status);
+    assert_eq!(vec![1, 3, 5], values(&body, "values"));
+    assert_eq!(Some("stream"), body.get("strategy").unwrap().as_str());
+    assert_eq!(Some(5), body.get("input_len").unwrap().as_u64());
+
+    let (status, body) = request(
+        addr,
+        "POST /least?k=2&largest&indices=true HTTP/1.1",
+        b"9 3 7 1 5",
+    );
+    assert_eq!(200, status);
+    assert_eq!(vec![9, 7], values(&body, "values"));
+    assert_eq!(vec![0, 2], values(&body, "indices"));
+}
+
+#[test]
+fn least_binary_body_with_strategy() {
+    let addr = start();
+    let l = make_list();
+    let bytes: Vec<u8> = l.iter().flat_map(|value| value.to_le_bytes()).collect();
+    let expected: Vec<u64> = implementation::naive(&l)
+        .into_iter()
+        .map(u64::from)
+        .collect();
+    let (status, body) = request(
+        addr,
+        "POST /least?k=8&strategy=optimized HTTP/1.1\r\nContent-Type: application/octet-stream",
+        &bytes,
+    );
+    assert_eq!(200, status);
+    assert_eq!(expected, values(&body, "values"));
+    assert_eq!(Some("optimized"), body.get("strategy").unwrap().as_str());
+    assert_eq!(
+        Some(l.len() as u64),
+        body.get("input_len").unwrap().as_u64()
+    );
+}
+
+#[test]
+fn strategies() {
+    let (status, body) = request(start(), "GET /strategies HTTP/1.1", b"");
+    assert_eq!(200, status);
+    let names: Vec<_> = body
+        .get("strategies")
+        .unwrap()
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|strategy| strategy.get("name").unwrap().as_str().unwrap())
+        .collect();
+    assert_eq!("stream", names[0]);
+    assert_eq!(
+        STRATEGIES
+            .iter()
+            .map(|strategy| strategy.name)
+            .collect::<Vec<_>>(),
+        names[1..]
+    );
+}
+
+#[test]
+fn errors() {
+    let addr = start();
+    for (head, status) in [
+        ("POST /least?strategy=bogus HTTP/1.1", 400),
+        ("POST /least?k=3&strategy=optimized HTTP/1.1", 400),
+        ("POST /least?frob=1 HTTP/1.1", 400),
+        ("POST /least?k=4000000000000 HTTP/1.1", 400),
+        ("GET /least HTTP/1.1", 405),
+        ("GET /nothing HTTP/1.1", 404),
+    ] {
+        let (got, body) = request(addr, head, b"1 2 3 4 5 6 7 8");
+        assert_eq!(status, got, "{head}");
+        assert!(body.get("error").is_some(), "{head}");
+    }
+    // the values would be collected for a strategy, the body is never sent
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "POST /least?strategy=optimized HTTP/1.1\r\nContent-Length: 1000000000000\r\n\r\n"
+    )
+    .unwrap();
+    stream.shutdown(Shutdown::Write).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 413 "), "{response}");
+
+    let (status, body) = request(addr, "POST /least HTTP/1.1", b"1 x 3");
+    assert_eq!(400, status);
+    assert!(body.get("error").unwrap().as_str().unwrap().contains("x"));
+}
+
+#[test]
+fn head_line_without_newline() {
+    let addr = start();
+    let mut stream = TcpStream::connect(addr).unwrap();
+    // exactly what the server reads before giving up, so nothing is left unread
+    let line = format!("GET /{}", "a".repeat(16 * 1024 + 1 - 5));
+    stream.write_all(line.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 431 "), "{response}");
+}
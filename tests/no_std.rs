@@ -0,0 +1,26 @@
+use std::path::PathBuf;
+use std::process::Command;
+
+// the library must keep building without `std`, and without `alloc`, also in `safe` mode, and
+// its tests must keep passing without them
+#[test]
+fn builds_without_std() {
+    let target = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("no_std");
+    let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_owned());
+    for command in ["check", "test"] {
+        for features in [&[][..], &["--features", "alloc"], &["--features", "safe"]] {
+            let status = Command::new(&cargo)
+                .args([command, "--lib", "--no-default-features", "--manifest-path"])
+                .arg(concat!(env!("CARGO_MANIFEST_DIR"), "/Cargo.toml"))
+                .arg("--target-dir")
+                .arg(&target)
+                .args(features)
+                .status()
+                .unwrap();
+            assert!(
+                status.success(),
+                "{command} --no-default-features {features:?}"
+            );
+        }
+    }
+}